@@ -0,0 +1,38 @@
+// all 9-step elements starting from Alphabet (with the old depth explorer).
+// `cargo run --release --example do_alphabet_9`
+
+use infinite_craft_shenanigance::{DEPTH_EXPLORER_MAX_STEPS, RecipesFile, RecipesState};
+use infinite_craft_shenanigance::old_depth_explorer::DepthExplorerVars;
+
+
+
+#[tokio::main]
+async fn main() {
+    // when `state` goes out of scope, it saves 1 final time
+    let mut state = RecipesState::with_autoload_and_save("alphabet 9.json", RecipesFile::JSONRecipesNum, 500_000).unwrap();
+    do_alphabet_9(&mut state).await;
+}
+
+
+
+async fn do_alphabet_9(state: &mut RecipesState) {
+    let de_vars = DepthExplorerVars {
+        stop_after_depth: DEPTH_EXPLORER_MAX_STEPS,  // modify the global variable, so the compiler knows how big stuff is gonna be -> SPEEEEED
+        split_start: 2,
+        lineage_elements: state.string_lineage_results(true, r#"
+
+Earth + Water = Plant
+Earth + Plant = Tree
+Tree + Water = River
+Earth + River = Delta
+River + Tree = Paper
+Paper + Tree = Book
+Book + Delta = Alphabet
+
+            "#),
+        ..Default::default()
+    };
+
+    let encountered = state.depth_explorer_split_start(&de_vars).await;
+    state.generate_lineages_file(&de_vars.lineage_elements, de_vars.stop_after_depth, &encountered).unwrap();
+}
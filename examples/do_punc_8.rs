@@ -0,0 +1,43 @@
+// all 8-step elements starting from Punctuation (with the old depth explorer).
+// `cargo run --release --example do_punc_8`
+
+use infinite_craft_shenanigance::{DEPTH_EXPLORER_MAX_STEPS, RecipesFile, RecipesState};
+use infinite_craft_shenanigance::old_depth_explorer::DepthExplorerVars;
+
+
+
+#[tokio::main]
+async fn main() {
+    // when `state` goes out of scope, it saves 1 final time
+    let mut state = RecipesState::with_autoload_and_save("punc 8.json", RecipesFile::JSONRecipesNum, 500_000).unwrap();
+    do_punc_8(&mut state).await;
+}
+
+
+
+async fn do_punc_8(state: &mut RecipesState) {
+    let de_vars = DepthExplorerVars {
+        stop_after_depth: DEPTH_EXPLORER_MAX_STEPS,  // modify the global variable, so the compiler knows how big stuff is gonna be -> SPEEEEED
+        split_start: 2,
+        lineage_elements: state.string_lineage_results(true, r#"
+
+Earth + Water = Plant
+Earth + Plant = Tree
+Tree + Water = River
+Earth + River = Delta
+River + Tree = Paper
+Paper + Tree = Book
+Book + Delta = Alphabet
+Alphabet + Alphabet = Word
+Word + Word = Sentence
+Sentence + Wind = Phrase
+Book + Phrase = Quote
+Alphabet + Quote = Punctuation
+
+            "#),
+        ..Default::default()
+    };
+
+    let encountered = state.depth_explorer_split_start(&de_vars).await;
+    state.generate_lineages_file(&de_vars.lineage_elements, de_vars.stop_after_depth, &encountered).unwrap();
+}
@@ -0,0 +1,35 @@
+// re-requests `Cloud + Rocket` to check what caps the server gives back.
+// `cargo run --release --example test_caps`
+
+use infinite_craft_shenanigance::{RecipesFile, RecipesState, sort_recipe_tuple};
+
+
+
+#[tokio::main]
+async fn main() {
+    // when `state` goes out of scope, it saves 1 final time
+    let mut state = RecipesState::with_autoload_and_save("depth_explorer_recipes.json", RecipesFile::JSONRecipesNum, 500_000).unwrap();
+    test_caps(&mut state).await;
+}
+
+
+
+async fn test_caps(state: &mut RecipesState) {
+    let recipe_tup = sort_recipe_tuple((state.str_to_num_fn("Rocket").unwrap(), state.str_to_num_fn("Cloud").unwrap()));
+    let result_num = *state.recipes_ing.get(&recipe_tup)
+        .expect("'Cloud + Rocket' is not in recipes_ing");
+
+    println!("result: {result_num} {}", state.num_to_str_fn(result_num));
+    
+    state.to_request_recipes.insert(recipe_tup);
+    
+
+    println!("{:?}", state.process_all_to_request_recipes("Test Caps").await);
+
+
+    let recipe_tup = sort_recipe_tuple((state.str_to_num_fn("Rocket").unwrap(), state.str_to_num_fn("Cloud").unwrap()));
+    let result_num = *state.recipes_ing.get(&recipe_tup)
+        .expect("'Cloud + Rocket' is not in recipes_ing");
+
+    println!("result: {result_num} {}", state.num_to_str_fn(result_num));
+}
@@ -0,0 +1,36 @@
+// explores from the base elements with the old depth explorer and writes a lineages file.
+// `cargo run --release --example test_depth_explorer`
+
+use infinite_craft_shenanigance::{DEPTH_EXPLORER_MAX_STEPS, RecipesFile, RecipesState};
+use infinite_craft_shenanigance::old_depth_explorer::DepthExplorerVars;
+
+
+
+#[tokio::main]
+async fn main() {
+    // when `state` goes out of scope, it saves 1 final time
+    let mut state = RecipesState::with_autoload_and_save("depth_explorer_recipes.json", RecipesFile::JSONRecipesNum, 500_000).unwrap();
+    test_depth_explorer(&mut state).await;
+}
+
+
+
+async fn test_depth_explorer(state: &mut RecipesState) {
+
+    // rerequest_all_nothing_recipes().await;
+
+    let de_vars = DepthExplorerVars {
+        stop_after_depth: DEPTH_EXPLORER_MAX_STEPS,  // modify the global variable, so the compiler knows how big stuff is gonna be -> SPEEEEED
+        split_start: 2,
+        lineage_elements: state.string_lineage_results(true, r#"
+
+
+            "#),
+        exclude_depth1_elements: vec![],
+        ..Default::default()
+    };
+
+    let encountered = state.depth_explorer_split_start(&de_vars).await;
+    // state.save("from_base_depth13_unknowns.json", recipe_loader::RecipeFileFormat::JSONRecipesNum).unwrap();
+    state.generate_lineages_file(&de_vars.lineage_elements,  de_vars.stop_after_depth, &encountered).expect("could not generate lineages file...");
+}
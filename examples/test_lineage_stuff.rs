@@ -0,0 +1,89 @@
+// --- LINEAGE GENERATION STUFF ---
+// generates lineages with all methods and then tries to improve a hand-made one with the old depth explorer.
+// `cargo run --release --example test_lineage_stuff`
+
+use infinite_craft_shenanigance::{RecipesFile, RecipesState};
+
+
+
+#[tokio::main]
+async fn main() {
+    // when `state` goes out of scope, it saves 1 final time
+    let mut state = RecipesState::with_autoload_and_save("depth_explorer_recipes.json", RecipesFile::JSONRecipesNum, 500_000).unwrap();
+    test_lineage_stuff(&mut state).await;
+}
+
+
+
+async fn test_lineage_stuff(state: &mut RecipesState) {
+    let recipes_result_map = state.get_recipes_result_map();
+    let recipes_uses_map = state.get_recipes_uses_map();
+    let mut heuristic_map = state.get_element_heuristic_map(&recipes_uses_map);
+
+    state.generate_lineage_multiple_methods(&["Unova Cap Pikachu"], &mut heuristic_map, &recipes_result_map, &recipes_uses_map, true);
+
+
+    let punc_alts = state.generate_lineage_multiple_methods(&["Punctuation", "Alphabet", "Delta"], &mut heuristic_map, &recipes_result_map, &recipes_uses_map, false);
+    punc_alts.print_lineages_ordered(state);
+
+    let ass_lineage = state.string_lineage_to_lineage(r#"
+Earth + Water = Plant
+Earth + Plant = Tree
+Tree + Water = River
+Earth + River = Delta
+River + Tree = Paper
+Paper + Tree = Book
+Book + Delta = Alphabet
+Alphabet + Alphabet = Word
+Word + Word = Sentence
+Sentence + Wind = Phrase
+Book + Phrase = Quote
+Alphabet + Quote = Punctuation
+Earth + Punctuation = Period
+Period + Wind = Comma
+Comma + Period = Semicolon
+Quote + Semicolon = Quotation Mark
+Alphabet + Fire = Alphabet Soup
+Alphabet Soup + Quotation Mark = "Alphabet Soup"
+Tree + Tree = Forest
+"Alphabet Soup" + Forest = "alphabet Trees"
+"alphabet Trees" + Wind = "alphabet Leaves"
+"alphabet Leaves" + Period = "alphabet Period"
+"alphabet Period" + Quote = "Quotation Marks"
+Quote + Quote = Wisdom
+Period + Period = Full Stop
+Book + Full Stop = End
+Alphabet Soup + Tree = Apple
+Apple + Word = iPad
+Alphabet + iPad = App
+App + App = App Store
+App Store + End = Append
+Append + Wisdom = Prepend
+"Quotation Marks" + Prepend = "Prepend"
+"Quotation Marks" + Punctuation = "Punctuation"
+"Punctuation" + Semicolon = "semicolon"
+"semicolon" + Comma = ";,"
+";," + Word = " ";
+" "; + "Prepend" = "prepend ";
+Alphabet + Earth = Planet
+Planet + Planet = Star
+Alphabet + Star = Asterisk
+"prepend "; + Asterisk = "prepend *"
+App + Quote = Tweet
+Phrase + Tweet = Hashtag
+"prepend *" + Hashtag = Prepend Hashtag
+" "; + Quotation Mark = " "
+" " + "prepend "; = "prepend "
+Append + Punctuation = Parenthesis
+Hashtag + Word = Trend
+Phrase + Trend = Meme
+Meme + Parenthesis = ( ͡° ͜ʖ ͡°)
+Semicolon + Semicolon = Colon
+( ͡° ͜ʖ ͡°) + Colon = :3
+:3 + "prepend " = "prepend :3"
+"prepend :3" + Prepend Hashtag = Prepend Hashtag :3
+ "#);
+
+    let improved_lineage = state.improve_lineage_depth_explorer(ass_lineage, &recipes_result_map, 1, 0).await;
+    improved_lineage.print_lineages_ordered(state);
+}
@@ -0,0 +1,100 @@
+# smoke tests for the python bindings, only needs the standard library.
+#
+#   cargo build --release --features python
+#   cp target/release/libinfinite_craft_shenanigance.so infinite_craft_shenanigance.so
+#   python3 -m unittest python/test_bindings.py
+#
+# (or `maturin develop --release` instead of the build + copy)
+# run it from the repo root, the recipe files are relative to it.
+
+import json
+import os
+import struct
+import sys
+import unittest
+
+sys.path.insert(0, os.getcwd())
+import infinite_craft_shenanigance as ics
+
+
+RECIPE_FILES_FOLDER = "Recipe Files"
+TEST_FILE = "python bindings test.json"
+
+NUM_TO_STR = ["Water", "Fire", "Earth", "Wind", "Nothing", "=unknown=", "Steam", "Plant", "Tree", "Mud", "Nothing Burger"]
+RECIPES = {
+    0: {1: 6, 2: 7, 10: 4},  # Water + Fire = Steam, Water + Earth = Plant, Water + Nothing Burger = Nothing
+    2: {7: 8, 10: 4},        # Earth + Plant = Tree, Earth + Nothing Burger = Nothing
+    1: {10: 4},              # Fire + Nothing Burger = Nothing
+}
+
+
+def u32s(data):
+    return list(struct.unpack(f"<{len(data) // 4}I", data))
+
+
+class TestBindings(unittest.TestCase):
+    @classmethod
+    def setUpClass(cls):
+        os.makedirs(RECIPE_FILES_FOLDER, exist_ok=True)
+        with open(os.path.join(RECIPE_FILES_FOLDER, TEST_FILE), "w") as f:
+            json.dump({"num_to_str": NUM_TO_STR, "recipes": RECIPES}, f)
+
+        cls.state = ics.RecipesState()
+        cls.state.load(TEST_FILE)
+
+    @classmethod
+    def tearDownClass(cls):
+        os.remove(os.path.join(RECIPE_FILES_FOLDER, TEST_FILE))
+
+    def test_lookup(self):
+        self.assertEqual(self.state.element_name(self.state.element_id("Tree")), "Tree")
+        self.assertEqual(self.state.neal_case("Nothing Burger"), "Nothing Burger")
+        self.assertEqual(len(self.state.element_names()), self.state.element_count())
+        with self.assertRaises(KeyError):
+            self.state.element_id("Definitely Not An Element")
+
+    def test_search(self):
+        self.assertEqual(self.state.search("tre")[0][1], "Tree")
+        self.assertEqual(self.state.search("Stean", mode="fuzzy")[0][1], "Steam")
+        self.assertEqual([x[1] for x in self.state.search("^(Mud|Tree)$", mode="regex")], ["Mud", "Tree"])
+        with self.assertRaises(KeyError) as e:
+            self.state.element_id("Plantt")
+        self.assertIn("Plant", str(e.exception))
+
+    def test_recipes(self):
+        self.assertEqual(self.state.recipe("Water", "Fire"), "Steam")
+        self.assertEqual(self.state.recipe("Fire", "Water"), "Steam")
+        self.assertIsNone(self.state.recipe("Steam", "Tree"))
+        self.assertEqual(self.state.recipes_for("Tree"), [("Earth", "Plant")])
+        self.assertIn(("Plant", "Tree"), self.state.uses_of("Earth"))
+
+        rows = u32s(self.state.recipes_array())
+        self.assertEqual(len(rows), 3 * self.state.recipe_count())
+        self.assertEqual(len(u32s(self.state.neal_case_map_array())), self.state.element_count())
+
+    def test_layer_explore(self):
+        base = ["Water", "Fire", "Earth", "Wind"]
+        elements, depths = self.state.layer_explore(base, 2, multi_thread=False)
+        depth_of = dict(zip(u32s(elements), u32s(depths)))
+        self.assertEqual(depth_of[self.state.element_id("Plant")], 1)
+        self.assertEqual(depth_of[self.state.element_id("Tree")], 2)
+
+    def test_lineage(self):
+        steps = self.state.lineage(["Tree"])
+        self.assertEqual(steps[-1][2], "Tree")
+        with self.assertRaises(KeyError):
+            self.state.lineage(["Definitely Not An Element"])
+
+    def test_dead_elements(self):
+        dead = [self.state.element_name(x) for x in u32s(self.state.dead_elements())]
+        self.assertEqual(dead, ["Nothing Burger"])
+
+    def test_save_and_reload(self):
+        self.state.save(TEST_FILE)
+        reloaded = ics.RecipesState()
+        reloaded.load(TEST_FILE)
+        self.assertEqual(reloaded.recipe_count(), self.state.recipe_count())
+
+
+if __name__ == "__main__":
+    unittest.main()
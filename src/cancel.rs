@@ -0,0 +1,64 @@
+use std::{sync::{Once, atomic::{AtomicBool, Ordering}}, thread};
+use tokio::sync::Notify;
+use colored::Colorize;
+
+
+
+/// shared cancellation token for the whole process.
+///
+/// **first Ctrl+C:** everything that checks `is_cancelled` stops starting new work.
+/// in-flight requests still finish, the explorers return what they have so far and the autosave runs.\
+/// **second Ctrl+C:** exits immediately. saving always writes to a temp file first,
+/// so this never leaves a half written recipe file behind.
+static CANCELLED: AtomicBool = AtomicBool::new(false);
+static CANCEL_NOTIFY: Notify = Notify::const_new();
+static INSTALL_HANDLER: Once = Once::new();
+
+
+/// starts listening for Ctrl+C (only the first call does something).
+/// it runs on its own thread with its own tiny runtime, so it also works from rayon or sync code.
+pub fn install_ctrl_c_handler() {
+    INSTALL_HANDLER.call_once(|| {
+        thread::spawn(|| {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .expect("could not build the ctrl+c runtime");
+
+            runtime.block_on(async {
+                loop {
+                    if tokio::signal::ctrl_c().await.is_err() {
+                        eprintln!("could not listen for Ctrl+C...");
+                        return;
+                    }
+                    if CANCELLED.swap(true, Ordering::SeqCst) {
+                        println!("\n{}", "[!] Second Ctrl+C detected! Aborting immediately...".red());
+                        std::process::exit(130);
+                    }
+                    println!("\n{}", "[!] Ctrl+C detected! Finishing in-flight work and saving... (press Ctrl+C again to abort immediately)".yellow());
+                    CANCEL_NOTIFY.notify_waiters();
+                }
+            });
+        });
+    });
+}
+
+
+pub fn is_cancelled() -> bool {
+    CANCELLED.load(Ordering::Relaxed)
+}
+
+/// cancels without a Ctrl+C, e.g. when some other stop condition is reached
+pub fn cancel() {
+    CANCELLED.store(true, Ordering::SeqCst);
+    CANCEL_NOTIFY.notify_waiters();
+}
+
+/// waits until the first Ctrl+C. (for `tokio::select!`)
+pub async fn cancelled() {
+    let notified = CANCEL_NOTIFY.notified();
+    tokio::pin!(notified);
+    notified.as_mut().enable();
+    if is_cancelled() { return; }
+    notified.await;
+}
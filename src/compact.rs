@@ -0,0 +1,105 @@
+//! dropping elements that nothing uses anymore and renumbering the rest densely.
+//!
+//! `num_to_str` never forgets an element, so after `remove_recipes_resulting_in`, `subtract_recipes`,
+//! `clean_element_names`, ... saved files still carry every name that ever existed.
+
+use std::time::Instant;
+use num_format::ToFormattedString;
+use colored::Colorize;
+
+use crate::{recipe_requestor::RequestPriority, structures::{Element, RecipesState, HARDCODED_IDS}};
+
+
+
+/// `old id -> new id`, `None` if the element got dropped. (index with the old id)
+pub type CompactIdMap = Vec<Option<Element>>;
+
+
+/// new id of an element, for callers that held on to ids from before `compact`
+pub fn compacted_id(id_map: &CompactIdMap, old_id: Element) -> Option<Element> {
+    id_map.get(old_id as usize).copied().flatten()
+}
+
+
+
+
+impl RecipesState {
+    /// drops every element that isn't used by a recipe (ingredient or result), a pending request,
+    /// or as the neal case version of a kept element. the hardcoded ids (base elements, `Nothing`, `=unknown=`) always stay.
+    ///
+    /// the kept elements keep their order, so the hardcoded ids don't change.
+    /// every id in the state gets rewritten, ids held somewhere else have to go through the returned map
+    /// (e.g. an `EncounteredElements` or `Lineage`s from before).
+    pub fn compact(&mut self) -> CompactIdMap {
+        let start_time = Instant::now();
+        let old_len = self.num_to_str.len();
+
+        let mut keep = vec![false; old_len];
+        keep[..HARDCODED_IDS.len()].fill(true);
+        for (&(f, s), &r) in &self.recipes_ing {
+            keep[f as usize] = true;
+            keep[s as usize] = true;
+            keep[r as usize] = true;
+        }
+        for recipe in self.to_request_recipes.iter() {
+            keep[recipe.0 as usize] = true;
+            keep[recipe.1 as usize] = true;
+        }
+        // neal case elements are their own neal case element, so one round is enough
+        for e in 0..old_len {
+            if keep[e] {
+                keep[self.neal_case_map[e] as usize] = true;
+            }
+        }
+
+        let mut id_map: CompactIdMap = vec![None; old_len];
+        let kept_ids = (0..old_len).filter(|&e| keep[e]);
+        for (new_id, e) in (0..).zip(kept_ids) {
+            id_map[e] = Some(new_id);
+        }
+        let new_id = |e: Element| id_map[e as usize].expect("kept elements only use kept elements");
+
+        let kept = |e: &usize| keep[*e];
+        self.num_to_str = std::mem::take(&mut self.num_to_str).into_iter().enumerate()
+            .filter(|(e, _)| kept(e))
+            .map(|(_, name)| name)
+            .collect();
+        self.neal_case_map = (0..old_len).filter(kept)
+            .map(|e| new_id(self.neal_case_map[e]))
+            .collect();
+        // (classified before some elements got added if it's shorter, those fall back to the length cutoff anyways.
+        // longer doesn't happen unless it's from another state, the extra ones don't belong to anything)
+        self.dead_elements.evidence = (0..self.dead_elements.evidence.len().min(old_len)).filter(kept)
+            .map(|e| self.dead_elements.evidence[e])
+            .collect();
+
+        self.recipes_ing = std::mem::take(&mut self.recipes_ing).into_iter()
+            .map(|((f, s), r)| ((new_id(f), new_id(s)), new_id(r)))
+            .collect();
+        let to_request: Vec<(Element, Element)> = self.to_request_recipes.iter().map(|recipe| *recipe).collect();
+        self.to_request_recipes.clear();
+        for (f, s) in to_request {
+            self.to_request_recipes.insert((new_id(f), new_id(s)));
+        }
+        if let Some(recipe_sources) = &mut self.provenance.recipe_sources {
+            *recipe_sources = std::mem::take(recipe_sources).into_iter()
+                .filter(|&((f, s), _)| keep[f as usize] && keep[s as usize])
+                .map(|((f, s), source)| ((new_id(f), new_id(s)), source))
+                .collect();
+        }
+        for priority in &mut self.request_priorities {
+            if let RequestPriority::ExplorerDepth(depth_map) = priority {
+                *depth_map = std::mem::take(depth_map).into_iter()
+                    .filter_map(|(e, depth)| Some((compacted_id(&id_map, e)?, depth)))
+                    .collect();
+            }
+        }
+
+        println!("Compacted elements in {:?} - dropped: {}, left: {}",
+            start_time.elapsed(),
+            (old_len - self.num_to_str.len()).to_formatted_string(&num_format::Locale::en).red(),
+            self.pretty_element_and_recipe_count(),
+        );
+        id_map
+    }
+}
@@ -0,0 +1,246 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs::{self, File}, io::{self, BufReader, BufWriter, Write}, time::Instant};
+use num_format::ToFormattedString;
+use colored::Colorize;
+
+use crate::{RECIPE_FILES_FOLDER, structures::{CaseMode, Element, RecipesState, NOTHING_ID, UNKNOWN_ID}};
+
+
+
+/// the explorers always banned every element that is longer than this (in bytes)
+pub const DEAD_ELEMENT_LENGTH_CUTOFF: usize = 30;
+
+
+/// how sure the classifier is that combining an element never gives anything (ordered, `None` < `High`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum DeadConfidence {
+    /// has real results (or nothing is known about it)
+    #[default]
+    None,
+    /// every tested combination is `Nothing`, but fewer than `min_tested_combinations` are tested
+    Low,
+    /// longer than the length cutoff, even though some combination has a real result
+    Medium,
+    /// every one of at least `min_tested_combinations` combinations is `Nothing`,
+    /// or it's longer than the length cutoff and never had a real result
+    High,
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeadClassifier {
+    /// names longer than this (in bytes) are dead
+    pub length_cutoff: usize,
+    /// how many combinations have to be known (and all `Nothing`) before an element counts as `High`
+    pub min_tested_combinations: u32,
+    /// elements with at least this confidence get the dead flag (and get banned by the explorers)
+    pub ban_from: DeadConfidence,
+}
+
+impl Default for DeadClassifier {
+    fn default() -> Self {
+        Self {
+            length_cutoff: DEAD_ELEMENT_LENGTH_CUTOFF,
+            min_tested_combinations: 50,
+            ban_from: DeadConfidence::Medium,
+        }
+    }
+}
+
+
+/// why an element got its confidence
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeadEvidence {
+    pub confidence: DeadConfidence,
+    /// combinations with a known result (`Nothing` or real) that use its `case_id` element
+    pub tested: u32,
+    /// how many of those are `Nothing`
+    pub nothing: u32,
+}
+
+
+/// the dead flag of every element. `RecipesState::dead_elements` holds one, the explorers ban by it.
+///
+/// the default one has nothing classified, so it falls back to the length cutoff (which is what the explorers always did).
+/// elements that got added after classifying (e.g. by requests) also fall back to the length cutoff.
+#[derive(Debug, Clone, Default)]
+pub struct DeadElements {
+    pub classifier: DeadClassifier,
+    /// the `case_mode` it got classified with
+    pub case_mode: CaseMode,
+    /// indexed by element id
+    pub evidence: Vec<DeadEvidence>,
+}
+
+
+/// how the dead flags are stored next to the database, by name so they survive id changes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct DeadElementsFile {
+    classifier: DeadClassifier,
+    /// files from before this was recorded are `Neal`
+    #[serde(default)]
+    case_mode: CaseMode,
+    /// only elements with a confidence above `None`
+    elements: BTreeMap<String, DeadEvidence>,
+}
+
+
+pub fn dead_elements_file_name(database_file_name: &str) -> String {
+    format!("{database_file_name}.dead.json")
+}
+
+
+
+
+impl DeadClassifier {
+    fn length_confidence(&self, name: &str, has_real_results: bool) -> DeadConfidence {
+        match (name.len() > self.length_cutoff, has_real_results) {
+            (false, _) => DeadConfidence::None,
+            (true, true) => DeadConfidence::Medium,
+            (true, false) => DeadConfidence::High,
+        }
+    }
+
+    fn nothing_confidence(&self, tested: u32, nothing: u32) -> DeadConfidence {
+        if tested == 0 || nothing < tested {
+            DeadConfidence::None
+        } else if tested < self.min_tested_combinations {
+            DeadConfidence::Low
+        } else {
+            DeadConfidence::High
+        }
+    }
+}
+
+
+
+
+impl RecipesState {
+    /// classifies every element with the recipes that are known right now, per `case_id`
+    /// (so with `CaseMode::Exact`, `hello world` isn't dead just because `Hello World` is).
+    /// set the result as `state.dead_elements` to make the explorers ban by it,
+    /// after changing the `case_mode` it has to be classified again.
+    pub fn classify_dead_elements(&self, classifier: DeadClassifier) -> DeadElements {
+        let start_time = Instant::now();
+
+        // (tested, nothing) per `case_id`
+        let mut counts: Vec<(u32, u32)> = vec![(0, 0); self.num_to_str.len()];
+        for (&(f, s), &r) in &self.recipes_ing {
+            if r == UNKNOWN_ID { continue; }
+            let (f, s) = (self.case_id(f), self.case_id(s));
+            let ingredients: &[Element] = if f == s { &[f] } else { &[f, s] };
+            for &i in ingredients {
+                counts[i as usize].0 += 1;
+                if r == NOTHING_ID {
+                    counts[i as usize].1 += 1;
+                }
+            }
+        }
+
+        let evidence: Vec<DeadEvidence> = (0..self.num_to_str.len())
+            .map(|e| {
+                let (tested, nothing) = counts[self.case_id(e as Element) as usize];
+                let confidence = if e as Element == NOTHING_ID || e as Element == UNKNOWN_ID {
+                    DeadConfidence::None
+                } else {
+                    classifier.nothing_confidence(tested, nothing)
+                        .max(classifier.length_confidence(&self.num_to_str[e], nothing < tested))
+                };
+                DeadEvidence { confidence, tested, nothing }
+            })
+            .collect();
+
+        let dead_elements = DeadElements { classifier, case_mode: self.case_mode, evidence };
+        println!("Classified dead elements in {:?} - dead: {}, suspected: {}",
+            start_time.elapsed(),
+            dead_elements.dead_count().to_formatted_string(&num_format::Locale::en).red(),
+            dead_elements.evidence.iter()
+                .filter(|e| e.confidence > DeadConfidence::None && e.confidence < classifier.ban_from)
+                .count().to_formatted_string(&num_format::Locale::en).yellow(),
+        );
+        dead_elements
+    }
+
+
+    /// the dead flag. falls back to the length cutoff for elements that aren't classified,
+    /// and for all of them if they got classified with another `case_mode`
+    pub fn is_dead_element(&self, element: Element) -> bool {
+        match self.dead_elements.evidence.get(element as usize) {
+            Some(evidence) if self.dead_elements.case_mode == self.case_mode => evidence.confidence >= self.dead_elements.classifier.ban_from,
+            _ => self.num_to_str[element as usize].len() > self.dead_elements.classifier.length_cutoff,
+        }
+    }
+
+
+    /// saves `self.dead_elements` into `{RECIPE_FILES_FOLDER}/{database}.dead.json`
+    pub fn save_dead_elements(&self, database_file_name: &str) -> io::Result<()> {
+        let file_path = format!("{RECIPE_FILES_FOLDER}/{}", dead_elements_file_name(database_file_name));
+        let temp_file_path = format!("{file_path}.tmp");
+
+        let file = DeadElementsFile {
+            classifier: self.dead_elements.classifier,
+            case_mode: self.dead_elements.case_mode,
+            elements: self.dead_elements.evidence.iter().enumerate()
+                .filter(|(_, evidence)| evidence.confidence > DeadConfidence::None)
+                .map(|(e, &evidence)| (self.num_to_str[e].clone(), evidence))
+                .collect(),
+        };
+
+        let mut writer = BufWriter::new(File::create(&temp_file_path)?);
+        serde_json::to_writer(&mut writer, &file)?;
+        writer.flush()?;
+        drop(writer);
+        fs::rename(temp_file_path, file_path)
+    }
+
+    /// loads the dead flags that `save_dead_elements` saved for this database into `self.dead_elements`.
+    /// elements that aren't in the file (e.g. new ones) only get the length cutoff.
+    pub fn load_dead_elements(&mut self, database_file_name: &str) -> io::Result<()> {
+        let file_path = format!("{RECIPE_FILES_FOLDER}/{}", dead_elements_file_name(database_file_name));
+        let reader = BufReader::new(File::open(file_path)?);
+        let file: DeadElementsFile = serde_json::from_reader(reader)?;
+
+        let evidence = self.num_to_str.iter()
+            .map(|name| file.elements.get(name).copied().unwrap_or_else(|| DeadEvidence {
+                confidence: file.classifier.length_confidence(name, true),
+                ..Default::default()
+            }))
+            .collect();
+        if file.case_mode != self.case_mode {
+            println!("{}", format!("[!] the dead elements of {database_file_name} are classified with {:?} case mode, but the state uses {:?}. classify them again (until then only the length cutoff counts)",
+                file.case_mode, self.case_mode).yellow());
+        }
+        self.dead_elements = DeadElements { classifier: file.classifier, case_mode: file.case_mode, evidence };
+        println!("Loaded {} dead elements", self.dead_elements.dead_count().to_string().red());
+        Ok(())
+    }
+}
+
+
+
+
+impl DeadElements {
+    pub fn dead_count(&self) -> usize {
+        self.evidence.iter().filter(|e| e.confidence >= self.classifier.ban_from).count()
+    }
+
+    /// writes every element with a confidence above `None` as `Element: High (12/12 Nothing)`
+    /// into the `RECIPE_FILES_FOLDER`, most confident first
+    pub fn write_to_file(&self, state: &RecipesState, file_name: &str) -> io::Result<()> {
+        let file = File::create(format!("{RECIPE_FILES_FOLDER}/{file_name}"))?;
+        let mut writer = BufWriter::new(file);
+
+        let mut elements: Vec<(&str, &DeadEvidence)> = self.evidence.iter().enumerate()
+            .filter(|(_, evidence)| evidence.confidence > DeadConfidence::None)
+            .map(|(e, evidence)| (state.num_to_str[e].as_str(), evidence))
+            .collect();
+        elements.sort_unstable_by_key(|&(name, evidence)| (std::cmp::Reverse(evidence.confidence), name));
+
+        writeln!(writer, "{:?}, case mode: {:?}, dead: {}\n", self.classifier, self.case_mode, self.dead_count())?;
+        for (name, evidence) in elements {
+            let dead = if evidence.confidence >= self.classifier.ban_from { " [dead]" } else { "" };
+            writeln!(writer, "{name}: {:?} ({}/{} Nothing){dead}", evidence.confidence, evidence.nothing, evidence.tested)?;
+        }
+        writer.flush()
+    }
+}
@@ -0,0 +1,182 @@
+use serde::{Deserialize, Serialize};
+use std::{fs::{self, File}, io::{self, BufReader, BufWriter, Write}, time::Instant};
+use rustc_hash::FxHashMap;
+use colored::Colorize;
+
+use crate::{
+    RECIPE_FILES_FOLDER,
+    cancel,
+    layer_explorer::LayerExplorer,
+    recipe_requestor::RequestPriority,
+    normalization::{NealCaseMove, NealCaseReport},
+    structures::{CaseMode, Element, ElementHeuristicMap, RecipesState, neal_case_name},
+};
+
+
+
+/// the proven minimal step count of every element up to `max_steps` (from the `LayerExplorer`).
+///
+/// `get_element_heuristic_map` adds up ingredient costs, so it overestimates whenever
+/// sub-lineages share elements. this doesn't, but it only knows elements up to `max_steps`.
+///
+/// it's stored by name next to the database (`{database}.depths.json`), so it survives id changes.
+///
+/// it only knows the recipes that were there when it got computed. if some were missing (`missing_recipes`),
+/// a missing one could make an element shallower, so the depths are only upper bounds then (see `is_exact`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DepthIndex {
+    pub base_elements: Vec<String>,
+    pub max_steps: usize,
+    #[serde(default)]
+    pub case_mode: CaseMode,
+    /// element name (neal case unless `CaseMode::Exact`) -> step count (base elements are 0)
+    pub depths: FxHashMap<String, usize>,
+    /// combinations the explorer ran into that aren't known yet (they got queued into `to_request_recipes`)
+    #[serde(default)]
+    pub missing_recipes: usize,
+}
+
+
+pub fn depth_index_file_name(database_file_name: &str) -> String {
+    format!("{database_file_name}.depths.json")
+}
+
+
+
+impl RecipesState {
+    /// runs the `LayerExplorer` from `base_elements` up to `max_steps` and keeps only the depths.
+    /// (this requests nothing, missing recipes just end up in `to_request_recipes` like always,
+    /// and make the index not exact). errors with `Interrupted` if it got canceled
+    pub fn compute_depth_index(&self, base_elements: &[Element], max_steps: usize) -> io::Result<DepthIndex> {
+        let start_time = Instant::now();
+
+        // take the pending requests out for the run, so whatever is in there afterwards is missing for this index
+        let pending: Vec<(Element, Element)> = self.to_request_recipes.iter().map(|recipe| *recipe).collect();
+        self.to_request_recipes.clear();
+        let encountered = LayerExplorer::start(self, base_elements, max_steps, true, false);
+        let missing_recipes = self.to_request_recipes.len();
+        for recipe in pending {
+            self.to_request_recipes.insert(recipe);
+        }
+
+        if cancel::is_cancelled() {
+            return Err(io::Error::new(io::ErrorKind::Interrupted, "canceled, the depth index would only be partial"));
+        }
+
+        // case variants share their `case_id` name, keep the smallest depth of them
+        let mut depths: FxHashMap<String, usize> = FxHashMap::default();
+        for (e, depth) in encountered.depth_map() {
+            let min_depth = depths.entry(self.num_to_str_fn(self.case_id(e))).or_insert(depth);
+            *min_depth = (*min_depth).min(depth);
+        }
+        for &e in base_elements {
+            depths.insert(self.num_to_str_fn(self.case_id(e)), 0);
+        }
+
+        println!("Computed depth index ({}-step, {} elements) in {:?}", max_steps, depths.len().to_string().purple(), start_time.elapsed());
+        if missing_recipes > 0 {
+            println!("{}", format!("[!] {missing_recipes} recipes are missing, the depths are only upper bounds until they're requested").yellow());
+        }
+        Ok(DepthIndex {
+            base_elements: self.num_to_strs_fn(base_elements),
+            max_steps,
+            case_mode: self.case_mode,
+            depths,
+            missing_recipes,
+        })
+    }
+}
+
+
+
+
+impl DepthIndex {
+    /// saves next to the database, into `{RECIPE_FILES_FOLDER}/{database}.depths.json`
+    pub fn save(&self, database_file_name: &str) -> io::Result<()> {
+        let file_path = format!("{RECIPE_FILES_FOLDER}/{}", depth_index_file_name(database_file_name));
+        let temp_file_path = format!("{file_path}.tmp");
+
+        let mut writer = BufWriter::new(File::create(&temp_file_path)?);
+        serde_json::to_writer(&mut writer, self)?;
+        writer.flush()?;
+        drop(writer);
+        fs::rename(temp_file_path, file_path)
+    }
+
+    pub fn load(database_file_name: &str) -> io::Result<Self> {
+        let file_path = format!("{RECIPE_FILES_FOLDER}/{}", depth_index_file_name(database_file_name));
+        let reader = BufReader::new(File::open(file_path)?);
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+
+    /// re-keys the depths with the current `neal_case_name` rules (an index from before they changed would miss those elements).
+    /// keys that end up on the same name keep the smaller depth. does nothing for `CaseMode::Exact`
+    pub fn recanonicalize(&mut self) -> NealCaseReport {
+        let mut report = NealCaseReport::default();
+        if self.case_mode == CaseMode::Exact {
+            return report;
+        }
+
+        let mut depths: FxHashMap<String, usize> = FxHashMap::default();
+        for (name, depth) in std::mem::take(&mut self.depths) {
+            let new_neal = neal_case_name(&name);
+            if new_neal != name {
+                report.moved.push(NealCaseMove { element: name.clone(), old_neal: name, new_neal: new_neal.clone() });
+            }
+            let entry = depths.entry(new_neal).or_insert(depth);
+            *entry = (*entry).min(depth);
+        }
+        self.depths = depths;
+        for name in &mut self.base_elements {
+            *name = neal_case_name(name);
+        }
+
+        println!("Recanonicalized depth index - moved: {}", report.moved.len().to_string().yellow());
+        report
+    }
+
+
+    /// if every recipe was known when it got computed (so the depths are proven minimal)
+    pub fn is_exact(&self) -> bool {
+        self.missing_recipes == 0
+    }
+
+
+    /// the exact depth, `None` if it's deeper than `max_steps` (or not craftable at all).
+    /// only an upper bound if it's not `is_exact` (and `None` doesn't mean anything then)
+    pub fn depth(&self, state: &RecipesState, element: Element) -> Option<usize> {
+        let element = match self.case_mode {
+            CaseMode::Neal => state.neal_case_map[element as usize],
+            CaseMode::Exact => element,
+        };
+        self.depths.get(&state.num_to_str[element as usize]).copied()
+    }
+
+    /// element id -> depth. everything that isn't in the index is `max_steps + 1`
+    pub fn element_depths(&self, state: &RecipesState) -> FxHashMap<Element, usize> {
+        (0..state.num_to_str.len() as Element)
+            .map(|e| (e, self.depth(state, e).unwrap_or(self.max_steps + 1)))
+            .collect()
+    }
+
+    /// `RequestPriority::ExplorerDepth` with true depths
+    pub fn request_priority(&self, state: &RecipesState) -> RequestPriority {
+        RequestPriority::ExplorerDepth(self.element_depths(state))
+    }
+
+    /// replaces heuristic costs with true depths for lineage generation.
+    /// elements that are too deep keep their heuristic, but never less than `max_steps + 1`.
+    /// if it's not `is_exact` it only lowers heuristics to the known depths
+    pub fn apply_to_heuristic_map(&self, state: &RecipesState, heuristic_map: &mut ElementHeuristicMap) {
+        let exact = self.is_exact();
+        for (e, heuristic) in heuristic_map.iter_mut().enumerate().take(state.num_to_str.len()) {
+            *heuristic = match (self.depth(state, e as Element), exact) {
+                (Some(depth), true) => depth as u64,
+                (Some(depth), false) => (*heuristic).min(depth as u64),
+                (None, true) => (*heuristic).max(self.max_steps as u64 + 1),
+                (None, false) => *heuristic,
+            };
+        }
+    }
+}
@@ -0,0 +1,158 @@
+use std::io;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use regex::{Regex, RegexBuilder};
+use rustc_hash::FxHashMap;
+
+use crate::structures::{Element, RecipesState};
+
+
+
+/// how `search_elements` matches the query against element names
+#[derive(Debug, Clone, Copy)]
+pub enum SearchMode {
+    Substring,
+    Prefix,
+    /// the query is a regex (`regex` crate syntax)
+    Regex,
+    /// levenshtein distance (in chars) of at most this much
+    Fuzzy(usize),
+}
+
+
+#[derive(Debug, Clone)]
+pub struct SearchResult {
+    pub id: Element,
+    pub name: String,
+    /// only known if a depth map was passed in (e.g. `EncounteredElements::depth_map`)
+    pub depth: Option<usize>,
+    /// lower is better. fuzzy: the edit distance, substring/prefix/regex: where the match starts
+    pub score: usize,
+}
+
+
+
+/// all the ways to find an element other than the exact `str_to_num_fn`.
+impl RecipesState {
+    /// searches all element names and returns the best `limit` candidates. ranked by:
+    /// exact matches, `score`, known depth (lower first), name length, id.
+    ///
+    /// `case_insensitive` compares lowercase names and only returns neal case elements
+    /// (so `hello world` and `Hello World` show up once, as `Hello World`).
+    pub fn search_elements(
+        &self, query: &str, mode: SearchMode, case_insensitive: bool, depths: Option<&FxHashMap<Element, usize>>, limit: usize
+    ) -> io::Result<Vec<SearchResult>> {
+        let matcher = NameMatcher::new(query, mode, case_insensitive)?;
+
+        let mut results: Vec<(bool, usize, Element)> = (0..self.num_to_str.len() as Element)
+            .into_par_iter()
+            .filter(|&id| !case_insensitive || self.neal_case_map[id as usize] == id)
+            .filter_map(|id| {
+                let (exact, score) = matcher.score(&self.num_to_str[id as usize])?;
+                Some((exact, score, id))
+            })
+            .collect();
+
+        let depth_of = |id: Element| depths.and_then(|d| {
+            d.get(&id).or_else(|| d.get(&self.neal_case_map[id as usize])).copied()
+        });
+        results.sort_unstable_by_key(|&(exact, score, id)| (
+            !exact,
+            score,
+            depth_of(id).unwrap_or(usize::MAX),
+            self.num_to_str[id as usize].len(),
+            id,
+        ));
+        results.truncate(limit);
+
+        Ok(results.into_iter()
+            .map(|(_, score, id)| SearchResult {
+                id,
+                name: self.num_to_str_fn(id),
+                depth: depth_of(id),
+                score,
+            })
+            .collect())
+    }
+
+
+    /// up to 5 similar element names, for "did you mean ...?" messages
+    pub fn did_you_mean(&self, name: &str) -> Vec<String> {
+        // roughly one typo every 4 chars
+        let max_distance = (name.chars().count() / 4).max(2);
+        self.search_elements(name, SearchMode::Fuzzy(max_distance), true, None, 5)
+            .map(|results| results.into_iter().map(|r| r.name).collect())
+            .unwrap_or_default()
+    }
+}
+
+
+
+/// matches one name against a query, shared by all searches over element names
+pub(crate) struct NameMatcher {
+    mode: SearchMode,
+    case_insensitive: bool,
+    regex: Option<Regex>,
+    query: String,
+    query_chars: Vec<char>,
+}
+
+impl NameMatcher {
+    pub(crate) fn new(query: &str, mode: SearchMode, case_insensitive: bool) -> io::Result<Self> {
+        let regex = match mode {
+            SearchMode::Regex => Some(RegexBuilder::new(query)
+                .case_insensitive(case_insensitive)
+                .build()
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?
+            ),
+            _ => None,
+        };
+        let query = if case_insensitive { query.to_lowercase() } else { query.to_string() };
+        let query_chars = query.chars().collect();
+        Ok(Self { mode, case_insensitive, regex, query, query_chars })
+    }
+
+    /// `(exact match, score)`, `None` if it doesn't match at all
+    pub(crate) fn score(&self, name: &str) -> Option<(bool, usize)> {
+        let lowercase_name;
+        let name = if self.case_insensitive && self.regex.is_none() {
+            lowercase_name = name.to_lowercase();
+            &lowercase_name
+        } else { name };
+
+        let score = match self.mode {
+            SearchMode::Substring => name.find(&self.query)?,
+            SearchMode::Prefix => if name.starts_with(&self.query) { 0 } else { return None },
+            SearchMode::Regex => self.regex.as_ref()?.find(name)?.start(),
+            SearchMode::Fuzzy(max_distance) => levenshtein_at_most(&self.query_chars, name, max_distance)?,
+        };
+        Some((*name == self.query, score))
+    }
+}
+
+
+
+/// levenshtein distance between `a` and `b`, or `None` if its more than `max_distance`
+fn levenshtein_at_most(a: &[char], b: &str, max_distance: usize) -> Option<usize> {
+    let b: Vec<char> = b.chars().collect();
+    if a.len().abs_diff(b.len()) > max_distance { return None; }
+
+    let mut prev_row: Vec<usize> = (0..=b.len()).collect();
+    let mut curr_row = vec![0; b.len() + 1];
+
+    for (i, &ca) in a.iter().enumerate() {
+        curr_row[0] = i + 1;
+        let mut row_min = curr_row[0];
+
+        for (j, &cb) in b.iter().enumerate() {
+            let substitution = prev_row[j] + usize::from(ca != cb);
+            curr_row[j + 1] = substitution.min(prev_row[j + 1] + 1).min(curr_row[j] + 1);
+            row_min = row_min.min(curr_row[j + 1]);
+        }
+        // every later row is at least this big, stop early
+        if row_min > max_distance { return None; }
+        std::mem::swap(&mut prev_row, &mut curr_row);
+    }
+
+    let distance = prev_row[b.len()];
+    (distance <= max_distance).then_some(distance)
+}
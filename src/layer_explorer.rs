@@ -1,10 +1,14 @@
-use std::{collections::hash_map, fmt::Debug, time::Instant};
+use std::{collections::hash_map, fmt::Debug, time::{Duration, Instant}};
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
 use rustc_hash::FxHashMap;
 use tinyvec::ArrayVec;
 use colored::Colorize;
 
-use crate::{DEPTH_EXPLORER_MAX_STEPS, structures::{Element, NOTHING_ID, RecipesState, sort_recipe_tuple}};
+use crate::{DEPTH_EXPLORER_MAX_STEPS, cancel, progress::Progress, structures::{Element, NOTHING_ID, RecipesState, sort_recipe_tuple}};
+
+
+/// how often a running explorer prints how many of its starting subsets are done
+const LAYER_EXPLORER_PROGRESS_INTERVAL: Duration = Duration::from_mins(1);
 
 
 /// This Algorithm generates all n-step elements starting from some ``base_elements``.
@@ -72,6 +76,8 @@ pub struct LayerExplorer<'a> {
     // cache: Vec<ArrayVec<[Element; LAYER_BASE_LENGTH + LAYER_STEPS - 2]>>,
 
     temp_results: Vec<Element>,
+    /// elements that are one recipe away from the base elements (only filled for `AltPolicy::FewestNonBase`)
+    one_step_elems: Vec<bool>,
 }
 
 #[derive(Clone)]
@@ -84,11 +90,19 @@ pub struct LayerData {
 impl LayerExplorer<'_> {
     pub fn start(
         recipes: &RecipesState, base_elements: &[Element], max_steps: usize, multi_thread: bool, generate_lineages_file: bool
+    ) -> EncounteredElements {
+        Self::start_with_alt_policy(recipes, base_elements, max_steps, multi_thread, generate_lineages_file, AltPolicy::All)
+    }
+
+    /// `start`, but only keeps the alt seeds that `alt_policy` wants (during the run, not just in the lineages file)
+    pub fn start_with_alt_policy(
+        recipes: &RecipesState, base_elements: &[Element], max_steps: usize, multi_thread: bool, generate_lineages_file: bool, alt_policy: AltPolicy
     ) -> EncounteredElements {
         let start_time = Instant::now();
+        cancel::install_ctrl_c_handler();
 
         let neal_base_elements: Vec<Element> = base_elements.iter()
-            .map(|&x| recipes.neal_case_map[x as usize])
+            .map(|&x| recipes.case_id(x))
             .collect();
 
         // start with the base_layer
@@ -99,27 +113,41 @@ impl LayerExplorer<'_> {
 
         let mut le = LayerExplorer {
             recipes,
-            encountered: EncounteredElements::default(),
+            encountered: EncounteredElements { alt_policy, ..Default::default() },
             layers: vec![base_element_layer],
             banned_elems: vec![false; recipes.num_to_str.len()],
             curr_steps: neal_base_elements.clone(),
             max_steps: max_steps + base_elements.len(),
             temp_results: Vec::new(),
+            one_step_elems: Vec::new(),
         };
 
+        if alt_policy == AltPolicy::FewestNonBase {
+            le.one_step_elems = vec![false; recipes.num_to_str.len()];
+            for (i, &first) in neal_base_elements.iter().enumerate() {
+                for &second in &neal_base_elements[..=i] {
+                    if let Some(&result) = recipes.recipes_ing.get(&sort_recipe_tuple((first, second))) {
+                        le.one_step_elems[recipes.case_id(result) as usize] = true;
+                    }
+                }
+            }
+        }
+
         // mark starting base_elements as banned
         for elem in neal_base_elements {
             le.banned_elems[elem as usize] = true;
             // le.cache[elem as usize] = [].into();
             
         }
-        // ban anything > 30 chars (dead elements)
+        // ban dead elements (by default anything > 30 chars)
         for (i, b) in le.banned_elems.iter_mut().enumerate() {
-            if recipes.num_to_str[i].len() > 30 {
+            if recipes.is_dead_element(i as Element) {
                 *b = true;
             }
         }
 
+        let progress = Progress::new(&format!("Layer Explorer {max_steps}-step"), 0);
+
         let final_encountered = if multi_thread {
             le.all_results_and_push_new_layer();
 
@@ -127,6 +155,10 @@ impl LayerExplorer<'_> {
             // extract all subsets (and finish the SubsetIter)
             let all_subsets: Vec<_> = (&mut last_layer.subset_iter).collect();
 
+            // progress = how many of the layer 1 subsets are fully explored
+            progress.set_total(all_subsets.len());
+            let _reporter = progress.spawn_reporter(LAYER_EXPLORER_PROGRESS_INTERVAL);
+
             all_subsets.into_par_iter()
                 .fold(
                     || le.encountered.clone(),
@@ -136,6 +168,7 @@ impl LayerExplorer<'_> {
                         thread_le.curr_steps.extend(start_subset);
 
                         thread_le.enter_main_loop();
+                        progress.inc(1);
                         thread_le.encountered
                     }
                 )
@@ -146,14 +179,27 @@ impl LayerExplorer<'_> {
             le.encountered
         };
 
-        
-        println!("Finished Layer Explorer! ({}) - Elements in {max_steps}-step: {} - to_request: {} - base_elements: {:?}",
+        let mut elements_per_depth = vec![0; max_steps + 1];
+        for seeds in final_encountered.elements.values() {
+            elements_per_depth[seeds[0].len() + 1] += 1;
+        }
+        for (depth, &count) in elements_per_depth.iter().enumerate().skip(1) {
+            progress.set_counter(&format!("depth_{depth}"), count);
+        }
+        progress.set_counter("to_request", recipes.to_request_recipes.len() as u64);
+        progress.export();
+
+        println!("Finished Layer Explorer! ({}) - Elements in {max_steps}-step: {} - to_request: {} - base_elements: {:?} - case mode: {:?}",
             format!("{:?}", start_time.elapsed()).yellow(),
             final_encountered.len().to_string().purple(),
             recipes.to_request_recipes.len().to_string().green(),
             recipes.num_to_strs_fn(base_elements),
+            recipes.case_mode,
         );
-        if generate_lineages_file {
+        if cancel::is_cancelled() {
+            println!("{}", "Layer Explorer got canceled, these are only partial results! (no lineages file)".red());
+        }
+        else if generate_lineages_file {
             recipes.generate_lineages_file(base_elements, max_steps, &final_encountered.elements)
                 .unwrap_or_else(|e| eprintln!("could not generate Lineages File... {e}"));
         }
@@ -180,6 +226,9 @@ impl LayerExplorer<'_> {
             if !recipes.to_request_recipes.is_empty() {
                 recipes.process_all_to_request_recipes(&format!("{i}-step")).await;
             }
+            if cancel::is_cancelled() {
+                return encountered;
+            }
             else if i == max_steps {
                 // we can return early
                 return encountered;
@@ -192,6 +241,8 @@ impl LayerExplorer<'_> {
 
     fn enter_main_loop(&mut self) {
         'main: loop {
+            // after a Ctrl+C just stop, whatever is in `encountered` is returned
+            if cancel::is_cancelled() { return; }
             self.all_results_and_push_new_layer();
 
             // now advance the iter, if its done, remove the layer and repeat.
@@ -228,12 +279,17 @@ impl LayerExplorer<'_> {
         let is_final_step = self.max_steps - self.curr_steps.len() == 1;
 
         macro_rules! process_result {
-            ($result:expr) => {
-                let neal_result = self.recipes.neal_case_map[$result as usize];
+            ($result:expr, $ing1:expr, $ing2:expr) => {
+                let neal_result = self.recipes.case_id($result);
 
                 if neal_result != NOTHING_ID {
+                    let alt_score = match self.encountered.alt_policy {
+                        AltPolicy::FewestNonBase => seed.iter().filter(|&&e| !self.one_step_elems[e as usize]).count(),
+                        AltPolicy::ShortestFinalNames => self.recipes.num_to_str[$ing1 as usize].len() + self.recipes.num_to_str[$ing2 as usize].len(),
+                        _ => 0,
+                    };
                     // add it to encountered before extra checks
-                    self.encountered.add_element($result, seed);
+                    self.encountered.add_element($result, seed, alt_score);
 
                     // extra checks for results-push
                     // add an element for processing only if there is more than 1 step left AND its not banned
@@ -256,7 +312,7 @@ impl LayerExplorer<'_> {
             for &ing2 in &self.curr_steps[..=top_layer.start_idx + i] {
                 let comb = sort_recipe_tuple((ing1, ing2));
                 if let Some(&result) = self.recipes.recipes_ing.get(&comb) {
-                    process_result!(result);
+                    process_result!(result, ing1, ing2);
                 } else { 
                     // comb does not exist, add it to the requests
                     self.recipes.to_request_recipes.insert(comb);
@@ -351,33 +407,91 @@ impl Iterator for SubsetIter {
 
 
 
+/// which of the equally short seeds `EncounteredElements` keeps for an element (the alts in the lineages file).
+/// the shortest seeds always win, this only decides between seeds of the same length.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AltPolicy {
+    /// every distinct seed (all alts, this gets big)
+    #[default]
+    All,
+    /// the first seed that was found
+    FirstOnly,
+    /// at most this many seeds
+    UpTo(usize),
+    /// seeds with the fewest elements that aren't one step away from the base elements
+    FewestNonBase,
+    /// seeds where the ingredients of the final recipe have the shortest names (summed up)
+    ShortestFinalNames,
+}
+
+impl AltPolicy {
+    /// if seeds of equal length are compared by their alt score
+    fn is_scored(self) -> bool {
+        matches!(self, Self::FewestNonBase | Self::ShortestFinalNames)
+    }
+
+    fn max_seeds(self) -> usize {
+        match self {
+            Self::FirstOnly => 1,
+            Self::UpTo(max_seeds) => max_seeds.max(1),
+            _ => usize::MAX,
+        }
+    }
+}
+
+
+enum SeedAction {
+    Replace,
+    Add,
+    Skip,
+}
+
+
 #[derive(Debug, Default, Clone)]
 pub struct EncounteredElements {
-    pub elements: FxHashMap<Element, Vec<Box<[Element]>>>
+    pub elements: FxHashMap<Element, Vec<Box<[Element]>>>,
+    pub alt_policy: AltPolicy,
+    /// the alt score (lower is better) of the kept seeds, only for scored policies
+    alt_scores: FxHashMap<Element, usize>,
+}
+
+fn seed_action(alt_policy: AltPolicy, existing: &[Box<[Element]>], existing_score: usize, seed: &[Element], alt_score: usize) -> SeedAction {
+    let mut ordering = seed.len().cmp(&existing[0].len());
+    if alt_policy.is_scored() {
+        ordering = ordering.then(alt_score.cmp(&existing_score));
+    }
+    match ordering {
+        // new seed is shorter (or better), collect it
+        std::cmp::Ordering::Less => SeedAction::Replace,
+        // equal, add it if there's still room
+        std::cmp::Ordering::Equal if existing.len() < alt_policy.max_seeds() && existing.iter().all(|s| **s != *seed) => SeedAction::Add,
+        // new seed is longer (or worse), do nothing
+        _ => SeedAction::Skip,
+    }
 }
 
+
 impl EncounteredElements {
-    pub fn add_element(&mut self, elem: Element, seed: &[Element]) {
+    /// `alt_score` is only looked at by scored alt policies (lower is better)
+    pub fn add_element(&mut self, elem: Element, seed: &[Element], alt_score: usize) {
+        let scored = self.alt_policy.is_scored();
         match self.elements.entry(elem) {
             hash_map::Entry::Occupied(mut occ) => {
                 let existing = occ.get_mut();
-                match seed.len().cmp(&existing[0].len()) {
-                    std::cmp::Ordering::Less => {
-                        // new seed is shorter, collect it
+                let existing_score = if scored { self.alt_scores.get(&elem).copied().unwrap_or(0) } else { 0 };
+                match seed_action(self.alt_policy, existing, existing_score, seed, alt_score) {
+                    SeedAction::Replace => {
                         existing.clear();
                         existing.push(seed.into());
+                        if scored { self.alt_scores.insert(elem, alt_score); }
                     },
-                    std::cmp::Ordering::Equal => {
-                        // equal length, add it
-                        if existing.iter().all(|s| **s != *seed) {
-                            existing.push(seed.into());
-                        }
-                    },
-                    std::cmp::Ordering::Greater => { }, // new seed is longer, do nothing
+                    SeedAction::Add => existing.push(seed.into()),
+                    SeedAction::Skip => {},
                 }
             }
             hash_map::Entry::Vacant(vac) => {
                 vac.insert(vec![seed.into()]);
+                if scored { self.alt_scores.insert(elem, alt_score); }
             }
         }
     }
@@ -385,41 +499,33 @@ impl EncounteredElements {
     pub fn len(&self) -> usize {
         self.elements.len()
     }
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+
+    /// the step count of every encountered element (seed length + 1).
+    /// useful for `RequestPriority::ExplorerDepth`
+    pub fn depth_map(&self) -> FxHashMap<Element, usize> {
+        self.elements.iter()
+            .map(|(&elem, seeds)| (elem, seeds[0].len() + 1))
+            .collect()
+    }
 
 
     pub fn merge_with(mut self, mut other_map: Self) -> Self {
         if other_map.len() > self.len() {
             (self, other_map) = (other_map, self);
         }
-    
+
         for (other_element, other_seeds) in other_map.elements {
-            match self.elements.entry(other_element) {
-                hash_map::Entry::Occupied(mut entry) => {
-                    let main_seeds = entry.get_mut();
-                    let main_len = main_seeds.first().unwrap().len(); // Assumes non-empty
-                    let other_len = other_seeds.first().unwrap().len();
-    
-                    match other_len.cmp(&main_len) {
-                        std::cmp::Ordering::Less => {
-                            // Local map's seeds are shorter, replace main's
-                            *main_seeds = other_seeds;
-                        }
-                        std::cmp::Ordering::Equal => {
-                            // Same length, add seeds from local map if not already present
-                            for other_seed in other_seeds {
-                                if !main_seeds.contains(&other_seed) {
-                                    main_seeds.push(other_seed);
-                                }
-                            }
-                        }
-                        std::cmp::Ordering::Greater => {
-                            // Main map's seeds are shorter, do nothing with this element
-                        }
-                    }
-                }
-                hash_map::Entry::Vacant(entry) => {
-                    entry.insert(other_seeds);
-                }
+            let alt_score = other_map.alt_scores.get(&other_element).copied().unwrap_or(0);
+            if let hash_map::Entry::Vacant(entry) = self.elements.entry(other_element) {
+                entry.insert(other_seeds);
+                if self.alt_policy.is_scored() { self.alt_scores.insert(other_element, alt_score); }
+                continue;
+            }
+            for other_seed in other_seeds {
+                self.add_element(other_element, &other_seed, alt_score);
             }
         }
         self
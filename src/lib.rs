@@ -0,0 +1,76 @@
+//! Infinite Craft recipe tools as a library:
+//! - [`RecipesState`] - all recipes, loading/merging/saving them ([`RecipesFile`] formats)
+//! - [`recipe_merge`] - what happens when loaded files disagree ([`recipe_merge::MergePolicy`]) and conflict reports
+//! - [`provenance`] - where every recipe came from (file or request + proxy)
+//! - [`recipe_diff`] - diffs between two databases, saved as patch files that can be applied to other databases
+//! - [`normalization`] - the neal case rules (`start_case_unicode`, `neal_case_name`) and rebuilding `neal_case_map` when they change
+//! - [`name_hygiene`] - names that only differ by invisible stuff (spaces, zero width, NFC/NFD), reported on load and merged
+//! - [`compact`] - drops elements that nothing uses anymore and renumbers the rest (old -> new id map)
+//! - [`subgraph`] - only the recipes within N steps of a seed, or everything that leads to some goals, as a new state
+//! - [`stats`] - recipe/element counts, degree/length/depth histograms (as a table or json)
+//! - [`reachability`] - everything craftable from a start set, and what blocks the rest
+//! - [`depth_index`] - exact minimal step counts from the [`LayerExplorer`], saved next to the database
+//! - [`dead_elements`] - which elements never combine into anything (with a confidence), the explorers ban by it
+//! - [`LayerExplorer`] - from a starting seed find all N-step away elements (and the ancient [`old_depth_explorer`])
+//! - [`lineage`] - lineage generation
+//! - [`lineages_file`] - streaming reader/writer/filter for the json lineages files the explorers write
+//! - [`lineages_index`] - element -> offset index of a lineages file for fast queries (lineages, depths, search)
+//! - [`lineages_diff`] - what changed between two lineages files (new elements, lower depths, other lineages)
+//! - [`element_search`] - substring/prefix/regex/fuzzy element search (and "did you mean" suggestions)
+//! - [`recipe_requestor`] - requesting missing recipes from a combination-proxy server
+//! - python bindings for all of this with the `python` feature (`src/python.rs`)
+//!
+//! `src/main.rs` and the `examples/` folder show how to use all of it.
+
+pub mod structures;
+pub mod normalization;
+pub mod name_hygiene;
+pub mod compact;
+pub mod subgraph;
+pub mod recipe_loader;
+pub mod lineage;
+pub mod old_depth_explorer;
+pub mod recipe_requestor;
+pub mod recipe_verifier;
+pub mod layer_explorer;
+pub mod progress;
+pub mod cancel;
+pub mod element_search;
+pub mod recipe_diff;
+pub mod recipe_merge;
+pub mod provenance;
+pub mod stats;
+pub mod reachability;
+pub mod depth_index;
+pub mod dead_elements;
+pub mod lineages_file;
+pub mod lineages_index;
+pub mod lineages_diff;
+#[cfg(feature = "python")]
+mod python;
+
+pub use structures::{Element, CaseMode, RecipesState, BASE_IDS, NOTHING_ID, UNKNOWN_ID, HARDCODED_IDS, sort_recipe_tuple, start_case_unicode, neal_case_name};
+pub use recipe_loader::RecipesFile;
+pub use layer_explorer::{LayerExplorer, EncounteredElements};
+pub use lineage::{Lineage, AltLineages};
+pub use recipe_requestor::{RequestPriority, RequestEndpoint, set_request_endpoints};
+
+
+
+
+/// just leave this to true
+pub const LINEAGES_FILE_COOL_JSON_MODE: bool = true;
+
+/// where all files are located that this code will access.
+/// this is relative to Cargo.toml file.
+/// you can also use a full path instead of a relative one
+pub const RECIPE_FILES_FOLDER: &str = "Recipe Files";
+
+/// to make the code faster it uses constant sized ``ArrayVecs``, meaning that they
+/// can't grow longer than this number:
+pub const DEPTH_EXPLORER_MAX_STEPS: usize = 10;
+
+/// only for ancient code
+pub const DEPTH_EXPLORER_JUST_MARK_UNKNOWN_NO_REQUESTS_NO_ENCOUNTERED: bool = false;
+/// only for ancient code
+pub const DEPTH_EXPLORER_DEPTH_GROW_FACTOR_GUESS: usize = 15;
@@ -4,12 +4,13 @@ use rustc_hash::{FxHashMap, FxHashSet};
 
 use crate::{
     old_depth_explorer::{DepthExplorerVars, Seed},
-    structures::{Element, RecipesState, sort_recipe_tuple, ElementHeuristicMap, RecipesResultICMap, RecipesUsesICMap, is_base_element, update_heuristic_map, start_case_unicode, BASE_IDS}
+    structures::{Element, RecipesState, sort_recipe_tuple, ElementHeuristicMap, RecipesResultICMap, RecipesUsesICMap, is_base_element, update_heuristic_map, BASE_IDS}
 };
 
 
 
 pub type LineageStep = [Element; 3];
+type LineageMethod<'a> = (&'static str, Box<dyn FnMut() -> Lineage + 'a>);
 
 #[derive(Debug, Clone, Eq)]
 pub struct Lineage {
@@ -265,9 +266,13 @@ impl RecipesState {
         recipes_uses_map: &RecipesUsesICMap,
         print_every_lineage: bool,
     ) -> AltLineages {
-        let goals: Vec<Element> = goals_str.iter().map(|&x| self.str_to_num_fn(&start_case_unicode(x)).unwrap()).collect();
+        let goals: Vec<Element> = goals_str.iter()
+            .map(|&x| self.str_to_num_fn(&self.case_name(x)).unwrap_or_else(|| {
+                panic!("goal '{x}' does not exist... did you mean: {:?}", self.did_you_mean(x))
+            }))
+            .collect();
     
-        let lineage_methods: Vec<(&str, Box<dyn FnMut() -> Lineage + '_>)> = vec![
+        let lineage_methods: Vec<LineageMethod<'_>> = vec![
             ("Simple Generational", Box::new(|| self.generate_lineage(&goals, &mut heuristic_map.clone(), recipes_result_map, recipes_uses_map, LineageRecalc::NoRecalc))),
             ("Recalc Left", Box::new(|| self.generate_lineage(&goals, &mut heuristic_map.clone(), recipes_result_map, recipes_uses_map, LineageRecalc::Left))),
             ("Recalc Right", Box::new(|| self.generate_lineage(&goals, &mut heuristic_map.clone(), recipes_result_map, recipes_uses_map, LineageRecalc::Right))),
@@ -326,7 +331,7 @@ impl RecipesState {
     
         while let Some(lineage) = alt_lineages.to_process.pop() {
             let initial_crafted: FxHashSet<Element> = BASE_IDS
-                .chain(lineage_elements.iter().map(|&x| self.neal_case_map[x as usize]))
+                .chain(lineage_elements.iter().map(|&x| self.case_id(x)))
                 .collect();
     
             let mut shorter_found = false;
@@ -344,7 +349,7 @@ impl RecipesState {
                 for (element, seeds) in encountered {
                     for seed in seeds {
                         let mut seed_and_element = seed;
-                        seed_and_element.elems.push(self.neal_case_map[element as usize]);
+                        seed_and_element.elems.push(self.case_id(element));
     
                         let seed_lineage = self.generate_lineage_from_results(seed_and_element, initial_crafted.clone(), recipes_result_map);
                         // println!("{:?} {:?}", debug_element_vec(&seed_and_element), debug_lineage_step_vec(&seed_lineage));
@@ -0,0 +1,146 @@
+use serde::{Deserialize, Serialize};
+use std::{fs::File, io::{self, BufWriter, Write}, time::Instant};
+use num_format::ToFormattedString;
+use colored::Colorize;
+
+use crate::{RECIPE_FILES_FOLDER, lineages_index::LineagesIndex};
+
+
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LineagesDepthChange {
+    pub element: String,
+    pub old_depth: usize,
+    pub new_depth: usize,
+}
+
+
+/// how many elements of each kind are at one depth (of the new file, removed elements count at their old depth)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LineagesDepthSummary {
+    pub depth: usize,
+    pub old_elements: usize,
+    pub new_elements: usize,
+    pub added: usize,
+    pub removed: usize,
+    /// elements that are at this depth now and needed more steps before
+    pub depth_dropped: usize,
+    pub lineage_changed: usize,
+}
+
+
+/// what changed between two `generate_lineages_file` outputs, e.g. before and after requesting more recipes.
+/// only the first (main) lineage of every element gets compared.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LineagesDiff {
+    pub old_file: String,
+    pub new_file: String,
+    /// `(element, depth)`
+    pub added: Vec<(String, usize)>,
+    /// `(element, old depth)`
+    pub removed: Vec<(String, usize)>,
+    pub depth_dropped: Vec<LineagesDepthChange>,
+    /// only happens if recipes got corrected (or the explorers ran with different settings)
+    pub depth_increased: Vec<LineagesDepthChange>,
+    /// same depth, different lineage. `(element, depth)`
+    pub lineage_changed: Vec<(String, usize)>,
+    pub per_depth: Vec<LineagesDepthSummary>,
+}
+
+
+
+/// compares two lineages files (in the `Lineages Files` folder) through their indexes, so neither gets loaded
+pub fn diff_lineages_files(old_file: &str, new_file: &str) -> io::Result<LineagesDiff> {
+    let old = LineagesIndex::open(old_file)?;
+    let new = LineagesIndex::open(new_file)?;
+    Ok(old.diff(&new))
+}
+
+
+
+impl LineagesIndex {
+    /// everything that changed from `self` (older) to `new`
+    pub fn diff(&self, new: &LineagesIndex) -> LineagesDiff {
+        let start_time = Instant::now();
+        let mut diff = LineagesDiff {
+            old_file: self.file_name.clone(),
+            new_file: new.file_name.clone(),
+            ..Default::default()
+        };
+
+        let max_depth = self.entries.iter().chain(&new.entries).map(|e| e.depth).max().unwrap_or(0);
+        let mut per_depth: Vec<LineagesDepthSummary> = (0..=max_depth)
+            .map(|depth| LineagesDepthSummary { depth, ..Default::default() })
+            .collect();
+
+        for old_entry in &self.entries {
+            per_depth[old_entry.depth].old_elements += 1;
+            if new.get(&old_entry.element).is_none() {
+                per_depth[old_entry.depth].removed += 1;
+                diff.removed.push((old_entry.element.clone(), old_entry.depth));
+            }
+        }
+
+        for new_entry in &new.entries {
+            let summary = &mut per_depth[new_entry.depth];
+            summary.new_elements += 1;
+
+            let Some(old_entry) = self.get(&new_entry.element) else {
+                summary.added += 1;
+                diff.added.push((new_entry.element.clone(), new_entry.depth));
+                continue;
+            };
+            let change = || LineagesDepthChange { element: new_entry.element.clone(), old_depth: old_entry.depth, new_depth: new_entry.depth };
+
+            if new_entry.depth < old_entry.depth {
+                summary.depth_dropped += 1;
+                diff.depth_dropped.push(change());
+            } else if new_entry.depth > old_entry.depth {
+                diff.depth_increased.push(change());
+            } else if new_entry.lineage_hash != old_entry.lineage_hash {
+                summary.lineage_changed += 1;
+                diff.lineage_changed.push((new_entry.element.clone(), new_entry.depth));
+            }
+        }
+
+        // depth 0 only exists if some entry had no lineage at all
+        diff.per_depth = per_depth.into_iter()
+            .filter(|s| s.depth != 0 || s.old_elements + s.new_elements != 0)
+            .collect();
+
+        println!("Diffed lineages files in {:?}", start_time.elapsed());
+        diff
+    }
+}
+
+
+
+
+impl LineagesDiff {
+    pub fn print_summary(&self) {
+        let fmt = |x: usize| x.to_formatted_string(&num_format::Locale::en);
+
+        println!("{} -> {}", self.old_file, self.new_file);
+        println!("  added: {}, removed: {}, depth dropped: {}, depth increased: {}, lineage changed: {}",
+            fmt(self.added.len()).green(),
+            fmt(self.removed.len()).red(),
+            fmt(self.depth_dropped.len()).green(),
+            fmt(self.depth_increased.len()).red(),
+            fmt(self.lineage_changed.len()).yellow(),
+        );
+        println!("  {:>5} {:>12} {:>12} {:>10} {:>10} {:>10} {:>10}", "depth", "old", "new", "added", "removed", "dropped", "changed");
+        for s in &self.per_depth {
+            println!("  {:>5} {:>12} {:>12} {:>10} {:>10} {:>10} {:>10}",
+                s.depth, fmt(s.old_elements), fmt(s.new_elements), fmt(s.added), fmt(s.removed), fmt(s.depth_dropped), fmt(s.lineage_changed),
+            );
+        }
+    }
+
+    /// writes the whole diff as pretty json into the `RECIPE_FILES_FOLDER`
+    pub fn write_json(&self, file_name: &str) -> io::Result<()> {
+        let file = File::create(format!("{RECIPE_FILES_FOLDER}/{file_name}"))?;
+        let mut writer = BufWriter::new(file);
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writer.flush()
+    }
+}
@@ -0,0 +1,604 @@
+#![deny(clippy::unwrap_used)]
+
+//! the json lineages file that `generate_lineages_file` writes:
+//! ```text
+//! {
+//! "elements_ran": ["Water", "Fire", ...],
+//! "case_mode": "neal",
+//! "element_count_stats": { "depth_1": 12, ..., "total": 345 },
+//! "elements": {
+//! "Steam": [[ ["Water", "Fire", "Steam"] ], [ ...alt... ]],
+//! ...
+//! }
+//! }
+//! ```
+//! the reader streams it (only one element entry is in memory at a time), so it works on multi-GB files.
+//! it is a real json parser, so it doesn't care about the layout, only about the structure.
+//!
+//! there is also a human readable text format (`TextLineagesWriter`), with a section per depth:
+//! ```text
+//! // ===== 2 Steps - 1 Elements =====
+//!
+//! // Cloud:
+//! Water + Fire = Steam
+//! Water + Steam = Cloud  // goal
+//! ```
+//! everything extra is in `//` comments, so `load_recipes_from_lineages_file(.., false)` can read it back.
+
+use serde::{Deserialize, Serialize, de::{self, DeserializeSeed, Deserializer, IgnoredAny, MapAccess, Visitor}};
+use std::{cell::Cell, fmt, fs::File, io::{self, BufReader, BufWriter, Read, Write}, rc::Rc, time::Instant};
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::{LINEAGES_FILE_COOL_JSON_MODE, RECIPE_FILES_FOLDER, structures::{CaseMode, RecipesState}};
+
+
+
+/// `[first, second, result]`
+pub type LineageRecipe = [String; 3];
+
+
+/// everything before `elements`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LineagesFileHeader {
+    /// the seed the explorer started with
+    pub elements_ran: Vec<String>,
+    /// how case variants were treated (files from before this was recorded are `Neal`)
+    #[serde(default)]
+    pub case_mode: CaseMode,
+    /// `depth_counts[i]` = how many elements need `i + 1` steps (`depth_{i + 1}` in the file)
+    pub depth_counts: Vec<usize>,
+    pub total: usize,
+}
+
+
+/// one element with all of its (equally short) lineages, the first one is the main one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineagesFileEntry {
+    pub element: String,
+    pub lineages: Vec<Vec<LineageRecipe>>,
+}
+
+
+/// gets every entry of a lineages file, in file order
+pub trait LineagesFileVisitor {
+    /// called once, right before the first entry
+    fn header(&mut self, _header: &LineagesFileHeader) -> io::Result<()> { Ok(()) }
+    fn entry(&mut self, entry: LineagesFileEntry) -> io::Result<()>;
+    /// `entry`, with the byte offset of the entry's value (right after its key, see `read_entry_at`)
+    fn entry_at(&mut self, entry: LineagesFileEntry, _offset: u64) -> io::Result<()> {
+        self.entry(entry)
+    }
+}
+
+impl<F: FnMut(LineagesFileEntry) -> io::Result<()>> LineagesFileVisitor for F {
+    fn entry(&mut self, entry: LineagesFileEntry) -> io::Result<()> {
+        self(entry)
+    }
+}
+
+
+/// which entries (and how many alts of them) to keep
+#[derive(Debug, Clone, Default)]
+pub struct LineagesFilter {
+    pub min_depth: Option<usize>,
+    pub max_depth: Option<usize>,
+    /// only these elements
+    pub elements: Option<FxHashSet<String>>,
+    /// keep at most this many lineages per element, `Some(1)` strips all alts
+    pub max_alts: Option<usize>,
+}
+
+
+/// options for the text lineages format
+#[derive(Debug, Clone, Default)]
+pub struct TextLineagesOptions {
+    /// element name -> emoji (e.g. from `load_emojis_from_ic_save_file`), shown in the `//` comments
+    pub emojis: Option<FxHashMap<String, String>>,
+    /// a list of every element and its depth at the end of the file
+    pub index: bool,
+}
+
+
+/// what `generate_lineages_file` writes
+#[derive(Debug, Clone)]
+pub enum LineagesFileFormat {
+    Json,
+    Text(TextLineagesOptions),
+}
+
+impl Default for LineagesFileFormat {
+    /// `LINEAGES_FILE_COOL_JSON_MODE` decides
+    fn default() -> Self {
+        if LINEAGES_FILE_COOL_JSON_MODE { Self::Json } else { Self::Text(TextLineagesOptions::default()) }
+    }
+}
+
+impl LineagesFileFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Text(_) => "txt",
+        }
+    }
+}
+
+
+/// where the lineages files are (`generate_lineages_file` writes into `Lineages Files` next to the `RECIPE_FILES_FOLDER`)
+pub fn lineages_file_path(file_name: &str) -> String {
+    format!("{RECIPE_FILES_FOLDER}/../Lineages Files/{file_name}")
+}
+
+
+
+
+impl LineagesFileEntry {
+    /// steps of the (first) lineage
+    pub fn depth(&self) -> usize {
+        self.lineages.first().map_or(0, Vec::len)
+    }
+
+    /// every recipe in every lineage
+    pub fn recipes(&self) -> impl Iterator<Item = &LineageRecipe> {
+        self.lineages.iter().flatten()
+    }
+}
+
+
+impl LineagesFilter {
+    pub fn strip_alts() -> Self {
+        Self { max_alts: Some(1), ..Default::default() }
+    }
+
+    pub fn keeps(&self, entry: &LineagesFileEntry) -> bool {
+        let depth = entry.depth();
+        self.min_depth.is_none_or(|min| depth >= min)
+            && self.max_depth.is_none_or(|max| depth <= max)
+            && self.elements.as_ref().is_none_or(|elements| elements.contains(&entry.element))
+    }
+
+    /// `None` if the entry gets filtered out
+    pub fn apply(&self, mut entry: LineagesFileEntry) -> Option<LineagesFileEntry> {
+        if !self.keeps(&entry) { return None; }
+        if let Some(max_alts) = self.max_alts {
+            entry.lineages.truncate(max_alts.max(1));
+        }
+        Some(entry)
+    }
+
+    /// if entries can get filtered out, `element_count_stats` has to be counted again
+    fn changes_counts(&self) -> bool {
+        self.min_depth.is_some() || self.max_depth.is_some() || self.elements.is_some()
+    }
+}
+
+
+
+
+pub struct LineagesFileReader<R: Read> {
+    reader: R,
+}
+
+impl LineagesFileReader<BufReader<File>> {
+    /// opens a file in the `Lineages Files` folder
+    pub fn open(file_name: &str) -> io::Result<Self> {
+        Ok(Self::new(BufReader::new(File::open(lineages_file_path(file_name))?)))
+    }
+}
+
+impl<R: Read> LineagesFileReader<R> {
+    /// `reader` should be buffered
+    pub fn new(reader: R) -> Self {
+        Self { reader }
+    }
+
+    /// streams every entry into `visitor`, returns the header
+    pub fn read(self, visitor: &mut impl LineagesFileVisitor) -> io::Result<LineagesFileHeader> {
+        let mut visitor_error = None;
+        let position = Rc::new(Cell::new(0));
+        let mut deserializer = serde_json::Deserializer::from_reader(CountingReader { reader: self.reader, position: position.clone() });
+        let result = deserializer.deserialize_map(FileVisitor { visitor, visitor_error: &mut visitor_error, position })
+            .and_then(|header| deserializer.end().map(|()| header));
+
+        // errors from the visitor went through serde as strings, give back the real one
+        match (result, visitor_error) {
+            (_, Some(e)) => Err(e),
+            (Ok(header), None) => Ok(header),
+            (Err(e), None) => Err(e.into()),
+        }
+    }
+
+    /// the header only (still has to go through all entries, they're just not kept)
+    pub fn read_header(self) -> io::Result<LineagesFileHeader> {
+        self.read(&mut |_| Ok(()))
+    }
+}
+
+
+/// keeps track of how many bytes serde_json took, for the entry offsets
+struct CountingReader<R> {
+    reader: R,
+    position: Rc<Cell<u64>>,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.reader.read(buf)?;
+        self.position.set(self.position.get() + n as u64);
+        Ok(n)
+    }
+}
+
+
+struct FileVisitor<'a, V> {
+    visitor: &'a mut V,
+    visitor_error: &'a mut Option<io::Error>,
+    position: Rc<Cell<u64>>,
+}
+
+impl<'de, V: LineagesFileVisitor> Visitor<'de> for FileVisitor<'_, V> {
+    type Value = LineagesFileHeader;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a lineages file object")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut header = LineagesFileHeader::default();
+        let mut header_sent = false;
+
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "elements_ran" => header.elements_ran = map.next_value()?,
+                "case_mode" => header.case_mode = map.next_value()?,
+                "element_count_stats" => {
+                    let stats: Vec<(String, usize)> = map.next_value::<serde_json::Map<String, serde_json::Value>>()?
+                        .into_iter()
+                        .filter_map(|(k, v)| Some((k, usize::try_from(v.as_u64()?).ok()?)))
+                        .collect();
+                    for (k, count) in stats {
+                        if k == "total" {
+                            header.total = count;
+                        } else if let Some(depth) = k.strip_prefix("depth_").and_then(|d| d.parse::<usize>().ok()).filter(|&d| d > 0) {
+                            if header.depth_counts.len() < depth { header.depth_counts.resize(depth, 0); }
+                            header.depth_counts[depth - 1] = count;
+                        }
+                    }
+                },
+                "elements" => {
+                    if !header_sent {
+                        header_sent = true;
+                        self.visitor.header(&header).map_err(|e| visitor_failed::<A::Error>(self.visitor_error, e))?;
+                    }
+                    map.next_value_seed(ElementsSeed { visitor: self.visitor, visitor_error: self.visitor_error, position: self.position.clone() })?;
+                },
+                _ => { map.next_value::<IgnoredAny>()?; },
+            }
+        }
+        if !header_sent {
+            self.visitor.header(&header).map_err(|e| visitor_failed::<A::Error>(self.visitor_error, e))?;
+        }
+        Ok(header)
+    }
+}
+
+
+struct ElementsSeed<'a, V> {
+    visitor: &'a mut V,
+    visitor_error: &'a mut Option<io::Error>,
+    position: Rc<Cell<u64>>,
+}
+
+impl<'de, V: LineagesFileVisitor> DeserializeSeed<'de> for ElementsSeed<'_, V> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de, V: LineagesFileVisitor> Visitor<'de> for ElementsSeed<'_, V> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an object of element -> lineages")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        while let Some(element) = map.next_key::<String>()? {
+            let offset = self.position.get();
+            let lineages: Vec<Vec<LineageRecipe>> = map.next_value()?;
+            self.visitor.entry_at(LineagesFileEntry { element, lineages }, offset)
+                .map_err(|e| visitor_failed::<A::Error>(self.visitor_error, e))?;
+        }
+        Ok(())
+    }
+}
+
+
+/// reads the lineages of one entry, `offset` is from `LineagesFileVisitor::entry_at`.
+/// (serde_json might have looked one byte further, so a `:` at the offset gets skipped)
+pub fn read_entry_at<R: io::BufRead + io::Seek>(mut reader: R, offset: u64) -> io::Result<Vec<Vec<LineageRecipe>>> {
+    reader.seek(io::SeekFrom::Start(offset))?;
+    loop {
+        let Some(&byte) = reader.fill_buf()?.first() else {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "lineages file ended at an entry offset"));
+        };
+        match byte {
+            b' ' | b'\n' | b'\r' | b'\t' | b':' => reader.consume(1),
+            _ => break,
+        }
+    }
+    let lineages = serde_json::Deserializer::from_reader(reader)
+        .into_iter::<Vec<Vec<LineageRecipe>>>()
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof, "no lineages at the entry offset"))??;
+    Ok(lineages)
+}
+
+
+fn visitor_failed<E: de::Error>(visitor_error: &mut Option<io::Error>, e: io::Error) -> E {
+    let error = E::custom(&e);
+    *visitor_error = Some(e);
+    error
+}
+
+
+
+
+/// writes the exact layout of `generate_lineages_file`
+pub struct LineagesFileWriter<W: Write> {
+    writer: W,
+    first_entry: bool,
+}
+
+impl LineagesFileWriter<BufWriter<File>> {
+    /// creates a file in the `Lineages Files` folder
+    pub fn create(file_name: &str, header: &LineagesFileHeader) -> io::Result<Self> {
+        Self::new(BufWriter::new(File::create(lineages_file_path(file_name))?), header)
+    }
+}
+
+impl<W: Write> LineagesFileWriter<W> {
+    /// writes the header right away
+    pub fn new(mut writer: W, header: &LineagesFileHeader) -> io::Result<Self> {
+        writeln!(writer, "{{")?;
+        writeln!(writer, "\"elements_ran\": [{}],", header.elements_ran.iter().map(|x| json_string(x)).collect::<Vec<_>>().join(", "))?;
+        writeln!(writer, "\"case_mode\": {},\n", serde_json::to_string(&header.case_mode)?)?;
+
+        writeln!(writer, "\"element_count_stats\": {{")?;
+        for (i, count) in header.depth_counts.iter().enumerate() {
+            writeln!(writer, "    \"depth_{}\": {},", i + 1, count)?;
+        }
+        writeln!(writer, "    \"total\": {}", header.total)?;
+        writeln!(writer, "}},\n\n\n")?;
+
+        writeln!(writer, "\"elements\": {{")?;
+        Ok(Self { writer, first_entry: true })
+    }
+
+    pub fn write_entry(&mut self, entry: &LineagesFileEntry) -> io::Result<()> {
+        let mut entry_string = format!("{}: [", json_string(&entry.element));
+        for (i, lineage) in entry.lineages.iter().enumerate() {
+            if i != 0 { entry_string.push(','); }
+            entry_string.push('[');
+            for (j, [f, s, r]) in lineage.iter().enumerate() {
+                if j != 0 { entry_string.push(','); }
+                entry_string.push_str(&format!("\n [{}, {}, {}]", json_string(f), json_string(s), json_string(r)));
+            }
+            entry_string.push_str("\n]");
+        }
+        entry_string.push(']');
+        self.write_raw_entry(&entry_string)
+    }
+
+    /// an already formatted `"Element": [...]` (see `get_encountered_entry`)
+    pub fn write_raw_entry(&mut self, entry_string: &str) -> io::Result<()> {
+        if !self.first_entry { write!(self.writer, ",\n\n")?; }
+        self.first_entry = false;
+        self.writer.write_all(entry_string.as_bytes())
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        if !self.first_entry { write!(self.writer, "\n\n")?; }
+        writeln!(self.writer, "}}")?;
+        write!(self.writer, "}}")?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+
+/// writes the text format, entries have to come sorted by depth (like in every lineages file)
+pub struct TextLineagesWriter<W: Write> {
+    writer: W,
+    options: TextLineagesOptions,
+    depth_counts: Vec<usize>,
+    current_depth: usize,
+    /// `(element, depth)` for the index
+    index: Vec<(String, usize)>,
+}
+
+impl<W: Write> TextLineagesWriter<W> {
+    /// writes the summary header right away
+    pub fn new(mut writer: W, header: &LineagesFileHeader, options: TextLineagesOptions) -> io::Result<Self> {
+        writeln!(writer, "// Lineages File - elements ran: {}", header.elements_ran.join(", "))?;
+        writeln!(writer, "// Case mode: {:?}", header.case_mode)?;
+        for (i, count) in header.depth_counts.iter().enumerate() {
+            writeln!(writer, "// {} Steps - {} Elements", i + 1, count)?;
+        }
+        writeln!(writer, "// Total Elements: {}", header.total)?;
+
+        Ok(Self { writer, options, depth_counts: header.depth_counts.clone(), current_depth: 0, index: Vec::new() })
+    }
+
+    pub fn write_entry(&mut self, entry: &LineagesFileEntry) -> io::Result<()> {
+        let depth = entry.depth();
+        if depth != self.current_depth {
+            self.current_depth = depth;
+            let count = depth.checked_sub(1).and_then(|i| self.depth_counts.get(i)).copied().unwrap_or(0);
+            write!(self.writer, "\n\n\n// ===== {depth} Steps - {count} Elements =====\n")?;
+        }
+
+        // a comment as well, so loading the file never mistakes a name like `1 + 1 = 2` for a recipe
+        if self.options.emojis.is_some() {
+            write!(self.writer, "\n// {}: {}\n", entry.element, emoji(&self.options.emojis, &entry.element))?;
+        } else {
+            write!(self.writer, "\n// {}:\n", entry.element)?;
+        }
+        for (i, lineage) in entry.lineages.iter().enumerate() {
+            if i != 0 { writeln!(self.writer, "// alt {}:", i + 1)?; }
+
+            for [f, s, r] in lineage {
+                write!(self.writer, "{f} + {s} = {r}")?;
+                let mut comments = Vec::new();
+                if self.options.emojis.is_some() {
+                    comments.push(format!("{} + {} = {}", emoji(&self.options.emojis, f), emoji(&self.options.emojis, s), emoji(&self.options.emojis, r)));
+                }
+                if *r == entry.element {
+                    comments.push("goal".to_string());
+                }
+                if !comments.is_empty() {
+                    write!(self.writer, "  // {}", comments.join(", "))?;
+                }
+                writeln!(self.writer)?;
+            }
+        }
+
+        if self.options.index {
+            self.index.push((entry.element.clone(), depth));
+        }
+        Ok(())
+    }
+
+    /// writes the index (if it's on)
+    pub fn finish(mut self) -> io::Result<W> {
+        if self.options.index {
+            self.index.sort_unstable();
+            write!(self.writer, "\n\n\n// ===== Index =====\n")?;
+            for (element, depth) in &self.index {
+                writeln!(self.writer, "// {element} - {depth}")?;
+            }
+        }
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+
+/// writes a json lineages file again as a text lineages file (both in the `Lineages Files` folder)
+pub fn convert_lineages_file_to_text(input_file: &str, output_file: &str, options: TextLineagesOptions) -> io::Result<()> {
+    let start_time = Instant::now();
+
+    struct Converter<'a> {
+        options: Option<TextLineagesOptions>,
+        output_file: &'a str,
+        writer: Option<TextLineagesWriter<BufWriter<File>>>,
+    }
+    impl LineagesFileVisitor for Converter<'_> {
+        fn header(&mut self, header: &LineagesFileHeader) -> io::Result<()> {
+            let writer = BufWriter::new(File::create(lineages_file_path(self.output_file))?);
+            self.writer = Some(TextLineagesWriter::new(writer, header, self.options.take().unwrap_or_default())?);
+            Ok(())
+        }
+        fn entry(&mut self, entry: LineagesFileEntry) -> io::Result<()> {
+            match &mut self.writer {
+                Some(writer) => writer.write_entry(&entry),
+                None => Ok(()),
+            }
+        }
+    }
+
+    let mut converter = Converter { options: Some(options), output_file, writer: None };
+    LineagesFileReader::open(input_file)?.read(&mut converter)?;
+    if let Some(writer) = converter.writer {
+        writer.finish()?;
+    }
+    println!("Converted lineages file {input_file} -> {output_file}: {:?}", start_time.elapsed());
+    Ok(())
+}
+
+
+fn emoji<'a>(emojis: &'a Option<FxHashMap<String, String>>, element: &str) -> &'a str {
+    emojis.as_ref()
+        .and_then(|emojis| emojis.get(element))
+        .map_or("?", String::as_str)
+}
+
+fn json_string(s: &str) -> String {
+    serde_json::to_string(s).unwrap_or_default()
+}
+
+
+
+
+/// copies the entries `filter` keeps into a new lineages file (both in the `Lineages Files` folder).
+/// if entries can get filtered out, the input gets read twice to count them for `element_count_stats`.
+pub fn filter_lineages_file(input_file: &str, output_file: &str, filter: &LineagesFilter) -> io::Result<LineagesFileHeader> {
+    let start_time = Instant::now();
+
+    let counted_header = filter.changes_counts().then(|| {
+        let mut header = LineagesFileHeader::default();
+        let mut counter = |entry: LineagesFileEntry| {
+            if filter.keeps(&entry) {
+                let depth = entry.depth().max(1);
+                if header.depth_counts.len() < depth { header.depth_counts.resize(depth, 0); }
+                header.depth_counts[depth - 1] += 1;
+                header.total += 1;
+            }
+            Ok(())
+        };
+        let elements_ran = LineagesFileReader::open(input_file)?.read(&mut counter)?.elements_ran;
+        io::Result::Ok(LineagesFileHeader { elements_ran, ..header })
+    }).transpose()?;
+
+    struct Copier<'a> {
+        filter: &'a LineagesFilter,
+        /// `None` = same as the input
+        header: Option<&'a LineagesFileHeader>,
+        output_file: &'a str,
+        writer: Option<LineagesFileWriter<BufWriter<File>>>,
+    }
+    impl LineagesFileVisitor for Copier<'_> {
+        fn header(&mut self, header: &LineagesFileHeader) -> io::Result<()> {
+            self.writer = Some(LineagesFileWriter::create(self.output_file, self.header.unwrap_or(header))?);
+            Ok(())
+        }
+        fn entry(&mut self, entry: LineagesFileEntry) -> io::Result<()> {
+            if let (Some(entry), Some(writer)) = (self.filter.apply(entry), &mut self.writer) {
+                writer.write_entry(&entry)?;
+            }
+            Ok(())
+        }
+    }
+
+    let mut copier = Copier {
+        filter,
+        header: counted_header.as_ref(),
+        output_file,
+        writer: None,
+    };
+    let input_header = LineagesFileReader::open(input_file)?.read(&mut copier)?;
+    if let Some(writer) = copier.writer {
+        writer.finish()?;
+    }
+
+    println!("Filtered lineages file {input_file} -> {output_file}: {:?}", start_time.elapsed());
+    Ok(counted_header.unwrap_or(input_header))
+}
+
+
+
+
+impl RecipesState {
+    /// adds every recipe of every lineage (alts too) in a json lineages file
+    pub fn load_recipes_from_json_lineages_file(&mut self, file_name: &str) -> io::Result<()> {
+        let mut str_to_num = self.get_str_to_num_map();
+        LineagesFileReader::open(file_name)?.read(&mut |entry: LineagesFileEntry| {
+            for [f, s, r] in entry.recipes() {
+                self.variables_add_recipe(f, s, r, &mut str_to_num);
+            }
+            Ok(())
+        })?;
+        Ok(())
+    }
+}
@@ -0,0 +1,195 @@
+//! an index for a json lineages file (element -> byte offset, depth, ...), so a 13-step file
+//! with all alts doesn't have to be grepped or loaded for every question.
+//! it's built with one streaming pass and saved next to the lineages file (`{file}.index.json`).
+
+use serde::{Deserialize, Serialize};
+use std::{fs::{self, File}, hash::{Hash, Hasher}, io::{self, BufReader, BufWriter, Write}, time::{Instant, UNIX_EPOCH}};
+use rustc_hash::{FxHashMap, FxHasher};
+use colored::Colorize;
+
+use crate::{
+    element_search::{NameMatcher, SearchMode},
+    lineages_file::{LineagesFileEntry, LineagesFileHeader, LineagesFileReader, LineagesFileVisitor, lineages_file_path, read_entry_at},
+};
+
+
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LineagesIndexEntry {
+    pub element: String,
+    /// steps of its lineage
+    pub depth: usize,
+    /// how many lineages it has (1 = no alts)
+    pub lineages: usize,
+    /// hash of the first lineage, to see if it changed between two files without reading them
+    pub lineage_hash: u64,
+    /// see `read_entry_at`
+    pub offset: u64,
+}
+
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LineagesIndex {
+    pub file_name: String,
+    /// size and modification time of the indexed file, the index is stale if they changed
+    pub file_len: u64,
+    pub file_modified: u128,
+    pub header: LineagesFileHeader,
+    /// in file order (sorted by depth, then name)
+    pub entries: Vec<LineagesIndexEntry>,
+
+    #[serde(skip)]
+    by_name: FxHashMap<String, usize>,
+}
+
+
+pub fn lineages_index_file_name(lineages_file_name: &str) -> String {
+    format!("{lineages_file_name}.index.json")
+}
+
+
+/// `(len, modified millis)` of a lineages file
+fn file_version(file_name: &str) -> io::Result<(u64, u128)> {
+    let metadata = fs::metadata(lineages_file_path(file_name))?;
+    let modified = metadata.modified().ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_millis());
+    Ok((metadata.len(), modified))
+}
+
+
+pub(crate) fn lineage_hash(entry: &LineagesFileEntry) -> u64 {
+    let mut hasher = FxHasher::default();
+    entry.lineages.first().hash(&mut hasher);
+    hasher.finish()
+}
+
+
+
+
+impl LineagesIndex {
+    /// loads the index if it's up to date, otherwise builds (and saves) a new one
+    pub fn open(file_name: &str) -> io::Result<Self> {
+        match Self::load(file_name) {
+            Ok(index) if file_version(file_name)? == (index.file_len, index.file_modified) => Ok(index),
+            Ok(_) => {
+                println!("{}", format!("[!] lineages index of {file_name} is stale, rebuilding").yellow());
+                Self::build_and_save(file_name)
+            },
+            Err(_) => Self::build_and_save(file_name),
+        }
+    }
+
+    fn build_and_save(file_name: &str) -> io::Result<Self> {
+        let index = Self::build(file_name)?;
+        index.save()?;
+        Ok(index)
+    }
+
+    /// streams through the whole lineages file once
+    pub fn build(file_name: &str) -> io::Result<Self> {
+        let start_time = Instant::now();
+        let (file_len, file_modified) = file_version(file_name)?;
+
+        struct Indexer(Vec<LineagesIndexEntry>);
+        impl LineagesFileVisitor for Indexer {
+            fn entry(&mut self, entry: LineagesFileEntry) -> io::Result<()> {
+                self.entry_at(entry, 0)
+            }
+            fn entry_at(&mut self, entry: LineagesFileEntry, offset: u64) -> io::Result<()> {
+                self.0.push(LineagesIndexEntry {
+                    depth: entry.depth(),
+                    lineages: entry.lineages.len(),
+                    lineage_hash: lineage_hash(&entry),
+                    offset,
+                    element: entry.element,
+                });
+                Ok(())
+            }
+        }
+
+        let mut indexer = Indexer(Vec::new());
+        let header = LineagesFileReader::open(file_name)?.read(&mut indexer)?;
+
+        let mut index = Self {
+            file_name: file_name.to_string(),
+            file_len,
+            file_modified,
+            header,
+            entries: indexer.0,
+            by_name: FxHashMap::default(),
+        };
+        index.fill_by_name();
+        println!("Indexed lineages file {file_name} ({} elements) in {:?}", index.entries.len().to_string().purple(), start_time.elapsed());
+        Ok(index)
+    }
+
+    fn fill_by_name(&mut self) {
+        self.by_name = self.entries.iter().enumerate()
+            .map(|(i, e)| (e.element.clone(), i))
+            .collect();
+    }
+
+
+    /// saves into the `Lineages Files` folder, next to the lineages file
+    pub fn save(&self) -> io::Result<()> {
+        let file_path = lineages_file_path(&lineages_index_file_name(&self.file_name));
+        let temp_file_path = format!("{file_path}.tmp");
+
+        let mut writer = BufWriter::new(File::create(&temp_file_path)?);
+        serde_json::to_writer(&mut writer, self)?;
+        writer.flush()?;
+        drop(writer);
+        fs::rename(temp_file_path, file_path)
+    }
+
+    pub fn load(file_name: &str) -> io::Result<Self> {
+        let reader = BufReader::new(File::open(lineages_file_path(&lineages_index_file_name(file_name)))?);
+        let mut index: Self = serde_json::from_reader(reader)?;
+        index.fill_by_name();
+        Ok(index)
+    }
+
+
+
+
+    pub fn get(&self, element: &str) -> Option<&LineagesIndexEntry> {
+        self.by_name.get(element).map(|&i| &self.entries[i])
+    }
+
+    pub fn depth(&self, element: &str) -> Option<usize> {
+        self.get(element).map(|e| e.depth)
+    }
+
+    /// reads the lineages (main one first, then the alts) of an element from the file
+    pub fn lineages(&self, element: &str) -> io::Result<Option<LineagesFileEntry>> {
+        let Some(entry) = self.get(element) else { return Ok(None) };
+        let reader = BufReader::new(File::open(lineages_file_path(&self.file_name))?);
+        let lineages = read_entry_at(reader, entry.offset)?;
+        Ok(Some(LineagesFileEntry { element: entry.element.clone(), lineages }))
+    }
+
+    /// every element that needs exactly `depth` steps, in file order
+    pub fn elements_at_depth(&self, depth: usize) -> Vec<&LineagesIndexEntry> {
+        self.entries.iter().filter(|e| e.depth == depth).collect()
+    }
+
+    /// like `search_elements`, ranked by: exact matches, score, depth, name length
+    pub fn search(&self, query: &str, mode: SearchMode, case_insensitive: bool, limit: usize) -> io::Result<Vec<&LineagesIndexEntry>> {
+        let matcher = NameMatcher::new(query, mode, case_insensitive)?;
+        let mut results: Vec<(bool, usize, &LineagesIndexEntry)> = self.entries.iter()
+            .filter_map(|e| {
+                let (exact, score) = matcher.score(&e.element)?;
+                Some((exact, score, e))
+            })
+            .collect();
+        results.sort_by_key(|&(exact, score, e)| (!exact, score, e.depth, e.element.len()));
+        results.truncate(limit);
+        Ok(results.into_iter().map(|(.., e)| e).collect())
+    }
+
+    /// `(depth here, depth in other)` of an element
+    pub fn compare_depth(&self, other: &LineagesIndex, element: &str) -> (Option<usize>, Option<usize>) {
+        (self.depth(element), other.depth(element))
+    }
+}
@@ -1,43 +1,26 @@
+// thin front-end for the library, the functions here are meant to be modified and called from `main`
 #![allow(dead_code)]
 
-mod structures;
-mod recipe_loader;
-mod lineage;
-mod old_depth_explorer;
-mod recipe_requestor;
-mod layer_explorer;
-
 use std::io;
 
-use crate::old_depth_explorer::DepthExplorerVars;
-use crate::layer_explorer::LayerExplorer;
-use crate::recipe_loader::{RecipesFile, strip_alts_from_json_lineages_file};
-use crate::structures::{Element, RecipesState, UNKNOWN_ID, sort_recipe_tuple};
-
-
-
-
-/// just leave this to true
-const LINEAGES_FILE_COOL_JSON_MODE: bool = true;
-
-/// where all files are located that this code will access.
-/// this is relative to Cargo.toml file.
-/// you can also use a full path instead of a relative one
-const RECIPE_FILES_FOLDER: &str = "Recipe Files";
-
-/// to make the code faster it uses constant sized ``ArrayVecs``, meaning that they
-/// can't grow longer than this number:
-const DEPTH_EXPLORER_MAX_STEPS: usize = 10;
-
-/// only for ancient code
-const DEPTH_EXPLORER_JUST_MARK_UNKNOWN_NO_REQUESTS_NO_ENCOUNTERED: bool = false;
-/// only for ancient code
-const DEPTH_EXPLORER_DEPTH_GROW_FACTOR_GUESS: usize = 15;
+use infinite_craft_shenanigance::{cancel, CaseMode, Element, LayerExplorer, RecipesFile, RecipesState, UNKNOWN_ID};
+use infinite_craft_shenanigance::recipe_loader::{load_emojis_from_ic_save_file, strip_alts_from_json_lineages_file};
+use infinite_craft_shenanigance::lineages_file::{TextLineagesOptions, convert_lineages_file_to_text};
+use infinite_craft_shenanigance::recipe_verifier::VerifySampling;
+use infinite_craft_shenanigance::dead_elements::DeadClassifier;
+use infinite_craft_shenanigance::layer_explorer::AltPolicy;
+use infinite_craft_shenanigance::element_search::SearchMode;
+use infinite_craft_shenanigance::lineages_index::LineagesIndex;
+use infinite_craft_shenanigance::lineages_diff::diff_lineages_files;
+use infinite_craft_shenanigance::depth_index::DepthIndex;
 
 
 
 #[tokio::main]
 async fn main() {
+    // first Ctrl+C finishes in-flight work and saves, second one aborts immediately
+    cancel::install_ctrl_c_handler();
+
     // --- LOAD RECIPES ---
     // there are 3 formats. if you load multiple recipe files, it simply merges them
     
@@ -49,6 +32,11 @@ async fn main() {
     // RecipeFileFormat::ICSaveFile
     // state.load("full_db.ic", recipe_loader::RecipeFileFormat::ICSaveFile).unwrap();
 
+    // --- METRICS ---
+    // progress of long runs always gets printed, but it can also be written to a file to graph it later:
+    // progress::set_metrics_export(progress::MetricsExport::JsonLines("run metrics.jsonl".to_string()));
+    // progress::set_metrics_export(progress::MetricsExport::Prometheus("run metrics.prom".to_string()));
+
     // you can comment this panic out
     // panic!("please look at src/main.rs and change what you need! (you can comment this panic out over there)");
 
@@ -81,12 +69,18 @@ fn fill_in_recipes() -> io::Result<()> {
     // these are all the files you want to fill it with. (should be in Recipe Files folder, next to src/.)
     // you can simply change the loads here
     let mut state = RecipesState::without_autosave();
+    // when two files disagree the later one wins, other policies are in `recipe_merge::MergePolicy`
+    // state.set_merge_policy(recipe_merge::MergePolicy::KeepBothAndFlag);
+    // remember which file every recipe came from (`state.recipes_from_source("scorpia fill.ic")`), set_merge_policy also does this
+    // state.track_provenance();
     state.load("depth_explorer_recipes.json", RecipesFile::JSONRecipesNum)?;
     state.load("alphabet 9.json", RecipesFile::JSONRecipesNum)?;
     state.load("punc 8.json", RecipesFile::JSONRecipesNum)?;
     state.load("more than Punc 8.json", RecipesFile::JSONRecipesNum)?;
     state.load("scorpia fill.ic", RecipesFile::ICSaveFile)?;
     // ...
+    // (needs `state.record_merge_conflicts()` before loading, unless the policy is `KeepBothAndFlag`)
+    // state.write_merge_conflicts("fill merge conflicts.txt")?;
     
     // this is the recipe file that contains all of the `=UNKNOWN=` recipes.
     // also should be in the Recipe Files Folder, next to src/.
@@ -126,6 +120,27 @@ pub fn calc_depth_13() {
     state.extract_to_request().save("13_missing_recipes.ic", RecipesFile::ICSaveFile).unwrap();
 }
 
+/// same as `calc_depth_13`, but only keeps up to 3 alts per element while running.
+/// (the all alts file is ~1.9M elements and needs a lot of RAM)
+pub fn calc_depth_13_few_alts() {
+    let mut state = RecipesState::without_autosave();
+    state.load("from_base 13.json", RecipesFile::JSONRecipesNum).unwrap();
+
+    let lineage_elems: Vec<Element> = state.string_lineage_results(true, "");
+    LayerExplorer::start_with_alt_policy(&state, &lineage_elems, 13, true, true, AltPolicy::UpTo(3));
+}
+
+/// 9-step elements where `Hello world` and `Hello World` are different elements (like in the game).
+/// the lineages file header says `"case_mode": "exact"`
+pub fn calc_depth_9_exact_case() {
+    let mut state = RecipesState::without_autosave();
+    state.load("from_base 13.json", RecipesFile::JSONRecipesNum).unwrap();
+    state.case_mode = CaseMode::Exact;
+
+    // Water, Fire, Earth, Wind
+    LayerExplorer::start(&state, &[0, 1, 2, 3], 9, true, true);
+}
+
 
 
 pub async fn test_layer_explorer() {
@@ -164,217 +179,160 @@ pub async fn requests_go_brr(file_name: &str, file_mode: RecipesFile) {
 
 
 
+pub async fn verify_recipes(file_name: &str, file_mode: RecipesFile) {
+    let mut state = RecipesState::without_autosave();
+    state.load(file_name, file_mode).unwrap();
 
+    // same seed = same sample, so a rerun after fixing something checks the exact same recipes
+    let report = state.verify_stored_recipes(10_000, VerifySampling::Stratified, 42, false).await;
+    report.write_to_file("verify report.txt").unwrap();
+}
 
 
 
-// --- OLD CODE ---
-// everything below this point is using the old depth explorer
-
+pub fn print_stats(file_name: &str, file_mode: RecipesFile) -> io::Result<()> {
+    let mut state = RecipesState::without_autosave();
+    state.load(file_name, file_mode)?;
 
-async fn test_depth_explorer(state: &mut RecipesState) {
+    let stats = state.stats(25);
+    stats.print_table();
+    stats.write_json("stats.json")
+}
 
-    // rerequest_all_nothing_recipes().await;
 
-    let de_vars = DepthExplorerVars {
-        stop_after_depth: DEPTH_EXPLORER_MAX_STEPS,  // modify the global variable, so the compiler knows how big stuff is gonna be -> SPEEEEED
-        split_start: 2,
-        lineage_elements: state.string_lineage_results(true, r#"
 
+pub fn unreachable_elements(file_name: &str, file_mode: RecipesFile) -> io::Result<()> {
+    let mut state = RecipesState::without_autosave();
+    state.load(file_name, file_mode)?;
 
-            "#),
-        exclude_depth1_elements: vec![],
-        ..Default::default()
-    };
+    // Water, Fire, Earth, Wind
+    let report = state.reachability(&[0, 1, 2, 3]);
+    report.write_to_file(&state, "unreachable elements.txt")?;
 
-    let encountered = state.depth_explorer_split_start(&de_vars).await;
-    // state.save("from_base_depth13_unknowns.json", recipe_loader::RecipeFileFormat::JSONRecipesNum).unwrap();
-    state.generate_lineages_file(&de_vars.lineage_elements,  de_vars.stop_after_depth, &encountered).expect("could not generate lineages file...");
+    state.add_unblocking_requests(&report);
+    state.extract_to_request().save("unblocking requests.ic", RecipesFile::ICSaveFile)
 }
 
 
 
+/// true depths for every element up to 11 steps, stored as `{file_name}.depths.json`.
+/// after that `DepthIndex::load(file_name)` can be used for `request_priority` and `apply_to_heuristic_map`.
+/// (missing recipes get saved as `missing_recipes`, request them and run it again to get exact depths)
+pub fn calc_depth_index(file_name: &str, file_mode: RecipesFile) -> io::Result<()> {
+    let mut state = RecipesState::without_autosave();
+    state.load(file_name, file_mode)?;
 
-async fn test_caps(state: &mut RecipesState) {
-    let recipe_tup = sort_recipe_tuple((state.str_to_num_fn("Rocket").unwrap(), state.str_to_num_fn("Cloud").unwrap()));
-    let result_num = *state.recipes_ing.get(&recipe_tup)
-        .expect("'Cloud + Rocket' is not in recipes_ing");
-
-    println!("result: {result_num} {}", state.num_to_str_fn(result_num));
-    
-    state.to_request_recipes.insert(recipe_tup);
-    
+    // Water, Fire, Earth, Wind
+    let depth_index = state.compute_depth_index(&[0, 1, 2, 3], 11)?;
+    depth_index.save(file_name)
+}
 
-    println!("{:?}", state.process_all_to_request_recipes("Test Caps").await);
 
 
-    let recipe_tup = sort_recipe_tuple((state.str_to_num_fn("Rocket").unwrap(), state.str_to_num_fn("Cloud").unwrap()));
-    let result_num = *state.recipes_ing.get(&recipe_tup)
-        .expect("'Cloud + Rocket' is not in recipes_ing");
+/// classifies dead elements (all `Nothing` / too long), writes `dead elements.txt`
+/// and stores the flags as `{file_name}.dead.json`.
+/// after that `state.load_dead_elements(file_name)` makes the explorers ban by them
+pub fn classify_dead_elements(file_name: &str, file_mode: RecipesFile) -> io::Result<()> {
+    let mut state = RecipesState::without_autosave();
+    state.load(file_name, file_mode)?;
 
-    println!("result: {result_num} {}", state.num_to_str_fn(result_num));
+    state.dead_elements = state.classify_dead_elements(DeadClassifier::default());
+    state.dead_elements.write_to_file(&state, "dead elements.txt")?;
+    state.save_dead_elements(file_name)
 }
 
 
 
+/// answers questions about a big lineages file without loading it
+/// (the index gets built on the first run and saved as `{file_name}.index.json`)
+pub fn query_lineages_file(file_name: &str, other_file_name: &str) -> io::Result<()> {
+    let index = LineagesIndex::open(file_name)?;
+
+    if let Some(entry) = index.lineages("Steam")? {
+        println!("{} ({} steps, {} lineages): {:?}", entry.element, entry.depth(), entry.lineages.len(), entry.lineages.first());
+    }
+    println!("3 step elements: {}", index.elements_at_depth(3).len());
+    for e in index.search("^Steam", SearchMode::Regex, true, 10)? {
+        println!("  - {} ({} steps)", e.element, e.depth);
+    }
 
+    let other_index = LineagesIndex::open(other_file_name)?;
+    println!("Steam depth: {:?}", index.compare_depth(&other_index, "Steam"));
+    Ok(())
+}
 
 
 
+/// what changed between two explorer runs, e.g. a 13-step run before and after requesting more recipes.
+/// prints a per depth summary and writes the full diff to `lineages diff.json`
+pub fn compare_lineages_files(old_file_name: &str, new_file_name: &str) -> io::Result<()> {
+    let diff = diff_lineages_files(old_file_name, new_file_name)?;
+    diff.print_summary();
+    diff.write_json("lineages diff.json")
+}
 
 
 
-async fn test_lineage_stuff(state: &mut RecipesState) {
-    // --- LINEAGE GENERATION STUFF ---
-
-    // recipe_loader::load("depth_explorer_recipes.json", recipe_loader::RecipeFileFormat::JSONRecipesNum).unwrap();
-    // let _auto_save = auto_load_and_save_recipes(
-    //     Duration::from_secs(30 * 60),
-    //     "depth_explorer_recipes.json",
-    //     recipe_loader::RecipeFileFormat::JSONRecipesNum
-    // );
-
-    let recipes_result_map = state.get_recipes_result_map();
-    let recipes_uses_map = state.get_recipes_uses_map();
-    let mut heuristic_map = state.get_element_heuristic_map(&recipes_uses_map);
-
-    state.generate_lineage_multiple_methods(&["Unova Cap Pikachu"], &mut heuristic_map, &recipes_result_map, &recipes_uses_map, true);
-
-
-    let punc_alts = state.generate_lineage_multiple_methods(&["Punctuation", "Alphabet", "Delta"], &mut heuristic_map, &recipes_result_map, &recipes_uses_map, false);
-    punc_alts.print_lineages_ordered(state);
-
-    let ass_lineage = state.string_lineage_to_lineage(r#"
-Earth + Water = Plant
-Earth + Plant = Tree
-Tree + Water = River
-Earth + River = Delta
-River + Tree = Paper
-Paper + Tree = Book
-Book + Delta = Alphabet
-Alphabet + Alphabet = Word
-Word + Word = Sentence
-Sentence + Wind = Phrase
-Book + Phrase = Quote
-Alphabet + Quote = Punctuation
-Earth + Punctuation = Period
-Period + Wind = Comma
-Comma + Period = Semicolon
-Quote + Semicolon = Quotation Mark
-Alphabet + Fire = Alphabet Soup
-Alphabet Soup + Quotation Mark = "Alphabet Soup"
-Tree + Tree = Forest
-"Alphabet Soup" + Forest = "alphabet Trees"
-"alphabet Trees" + Wind = "alphabet Leaves"
-"alphabet Leaves" + Period = "alphabet Period"
-"alphabet Period" + Quote = "Quotation Marks"
-Quote + Quote = Wisdom
-Period + Period = Full Stop
-Book + Full Stop = End
-Alphabet Soup + Tree = Apple
-Apple + Word = iPad
-Alphabet + iPad = App
-App + App = App Store
-App Store + End = Append
-Append + Wisdom = Prepend
-"Quotation Marks" + Prepend = "Prepend"
-"Quotation Marks" + Punctuation = "Punctuation"
-"Punctuation" + Semicolon = "semicolon"
-"semicolon" + Comma = ";,"
-";," + Word = " ";
-" "; + "Prepend" = "prepend ";
-Alphabet + Earth = Planet
-Planet + Planet = Star
-Alphabet + Star = Asterisk
-"prepend "; + Asterisk = "prepend *"
-App + Quote = Tweet
-Phrase + Tweet = Hashtag
-"prepend *" + Hashtag = Prepend Hashtag
-" "; + Quotation Mark = " "
-" " + "prepend "; = "prepend "
-Append + Punctuation = Parenthesis
-Hashtag + Word = Trend
-Phrase + Trend = Meme
-Meme + Parenthesis = ( ͡° ͜ʖ ͡°)
-Semicolon + Semicolon = Colon
-( ͡° ͜ʖ ͡°) + Colon = :3
-:3 + "prepend " = "prepend :3"
-"prepend :3" + Prepend Hashtag = Prepend Hashtag :3
- "#);
-
-    let improved_lineage = state.improve_lineage_depth_explorer(ass_lineage, &recipes_result_map, 1, 0).await;
-    improved_lineage.print_lineages_ordered(state);
+/// rewrites a json lineages file as a human readable text file (with emojis from a save file and an index).
+/// loading it back works with `load_recipes_from_lineages_file(.., false)`
+pub fn lineages_file_to_text(file_name: &str, emoji_save_file: &str) -> io::Result<()> {
+    let options = TextLineagesOptions {
+        emojis: Some(load_emojis_from_ic_save_file(emoji_save_file)?),
+        index: true,
+    };
+    convert_lineages_file_to_text(file_name, &format!("{file_name}.txt"), options)
 }
 
 
 
+/// re-keys `{file_name}.depths.json` after the neal case rules changed (writes every moved name to `neal case changes.txt`).
+/// the database itself doesn't need anything, loading it already rebuilds the `neal_case_map`
+pub fn recanonicalize_depth_index(file_name: &str) -> io::Result<()> {
+    let mut depth_index = DepthIndex::load(file_name)?;
 
+    let report = depth_index.recanonicalize();
+    report.write_to_file("neal case changes.txt")?;
+    depth_index.save(file_name)
+}
 
 
 
+/// merges elements like `Steam ` or `Cafe\u{301}` into `Steam` and `Café` (writes every merge to `name cleanup.txt`)
+pub fn clean_element_names(file_name: &str, file_mode: RecipesFile) -> io::Result<()> {
+    let mut state = RecipesState::without_autosave();
+    state.load(file_name, file_mode)?;
 
+    let report = state.clean_element_names();
+    report.write_to_file("name cleanup.txt")?;
+    state.save(file_name, file_mode)
+}
 
-async fn do_punc_8(state: &mut RecipesState) {
-    // when this _auto_save goes out of scope, it saves 1 final time
-    // let _auto_save = auto_load_and_save_recipes(
-    //     Duration::from_secs(30 * 60),
-    //     "punc 8.json",
-    //     recipe_loader::RecipeFileFormat::JSONRecipesNum
-    // );
-
-    let de_vars = DepthExplorerVars {
-        stop_after_depth: DEPTH_EXPLORER_MAX_STEPS,  // modify the global variable, so the compiler knows how big stuff is gonna be -> SPEEEEED
-        split_start: 2,
-        lineage_elements: state.string_lineage_results(true, r#"
 
-Earth + Water = Plant
-Earth + Plant = Tree
-Tree + Water = River
-Earth + River = Delta
-River + Tree = Paper
-Paper + Tree = Book
-Book + Delta = Alphabet
-Alphabet + Alphabet = Word
-Word + Word = Sentence
-Sentence + Wind = Phrase
-Book + Phrase = Quote
-Alphabet + Quote = Punctuation
 
-            "#),
-        ..Default::default()
-    };
+/// only keeps the recipes that don't result in `=unknown=`, and drops every element that was only used by those
+pub fn drop_unknown_recipes(file_name: &str, file_mode: RecipesFile) -> io::Result<()> {
+    let mut state = RecipesState::without_autosave();
+    state.load(file_name, file_mode)?;
 
-    let encountered = state.depth_explorer_split_start(&de_vars).await;
-    state.generate_lineages_file(&de_vars.lineage_elements, de_vars.stop_after_depth, &encountered).unwrap();
+    state.remove_recipes_resulting_in(&[UNKNOWN_ID]);
+    state.compact();
+    state.save(&format!("{file_name} (compacted)"), file_mode)
 }
 
 
 
-async fn do_alphabet_9(state: &mut RecipesState) {
-    // when this _auto_save goes out of scope, it saves 1 final time
-    // let _auto_save = auto_load_and_save_recipes(
-    //     Duration::from_secs(30 * 60),
-    //     "alphabet 9.json",
-    //     recipe_loader::RecipeFileFormat::JSONRecipesNum
-    // );
+/// small databases to share: everything within 9 steps of the base elements, and everything that leads to Punctuation
+pub fn extract_shareable_recipes(file_name: &str, file_mode: RecipesFile) -> io::Result<()> {
+    let mut state = RecipesState::without_autosave();
+    state.load(file_name, file_mode)?;
 
-    let de_vars = DepthExplorerVars {
-        stop_after_depth: DEPTH_EXPLORER_MAX_STEPS,  // modify the global variable, so the compiler knows how big stuff is gonna be -> SPEEEEED
-        split_start: 2,
-        lineage_elements: state.string_lineage_results(true, r#"
+    // Water, Fire, Earth, Wind
+    state.extract_within_steps(&[0, 1, 2, 3], 9).save("from_base 9 (only).json", RecipesFile::JSONRecipesNum)?;
 
-Earth + Water = Plant
-Earth + Plant = Tree
-Tree + Water = River
-Earth + River = Delta
-River + Tree = Paper
-Paper + Tree = Book
-Book + Delta = Alphabet
+    let punctuation = state.str_to_num_fn("Punctuation").ok_or_else(|| io::Error::other("Punctuation doesn't exist"))?;
+    state.extract_ancestors(&[punctuation]).save("punctuation ancestors.ic", RecipesFile::ICSaveFile)
+}
 
-            "#),
-        ..Default::default()
-    };
 
-    let encountered = state.depth_explorer_split_start(&de_vars).await;
-    state.generate_lineages_file(&de_vars.lineage_elements, de_vars.stop_after_depth, &encountered).unwrap();
-}
\ No newline at end of file
+// the old examples (lineage generation, the old depth explorer, ...) are in the `examples/` folder now.
+// run them with `cargo run --release --example do_punc_8`
@@ -0,0 +1,296 @@
+//! element names that only differ by invisible stuff (spaces at the ends, zero width chars, NFC vs NFD, control chars).
+//! they come in with merged files, and because elements are keyed by their exact string they end up as
+//! phantom duplicates of the real element (with their own recipes).
+//!
+//! every `load` checks the names (see `NameHygiene`), `clean_element_names` renames them
+//! or merges them into the element they should have been.
+
+use serde::{Deserialize, Serialize};
+use std::{fs::File, io::{self, BufWriter, Write}, time::Instant};
+use num_format::ToFormattedString;
+use unicode_normalization::{UnicodeNormalization, is_nfc};
+use colored::Colorize;
+
+use crate::{RECIPE_FILES_FOLDER, recipe_requestor::RequestPriority, structures::{Element, RecipesState, HARDCODED_IDS, sort_recipe_tuple}};
+
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum NameIssue {
+    /// leading or trailing (unicode) whitespace
+    OuterWhitespace,
+    /// zero width spaces, soft hyphens, bidi marks, ... anywhere, and zero width (non) joiners at the ends.
+    /// (joiners in the middle are left alone, emojis like 👨‍👩‍👧 need them)
+    ZeroWidth,
+    Control,
+    /// not in NFC, e.g. `e` + combining accent instead of `é`
+    NotNfc,
+}
+
+
+/// what `load` does with bad names
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NameHygiene {
+    Ignore,
+    /// prints how many there are
+    #[default]
+    Report,
+    /// `clean_element_names` after every load
+    Clean,
+}
+
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NameProblem {
+    pub element: String,
+    pub issues: Vec<NameIssue>,
+    /// what `clean_name` makes of it (empty if there's nothing left)
+    pub clean: String,
+}
+
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NameMerge {
+    pub from: String,
+    pub into: String,
+    /// recipes that used `from` (as an ingredient or result) and got rewritten
+    pub recipes: usize,
+}
+
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NameHygieneReport {
+    pub problems: Vec<NameProblem>,
+    /// `(old, new)`, the clean name didn't exist yet so the element just got renamed
+    pub renamed: Vec<(String, String)>,
+    /// the clean name already existed, every recipe now uses that element
+    pub merges: Vec<NameMerge>,
+    /// rewritten recipes that already existed with another result (decided by the `MergePolicy`, see `record_merge_conflicts`)
+    pub recipe_conflicts: usize,
+}
+
+
+
+fn is_zero_width(c: char) -> bool {
+    matches!(c,
+        '\u{AD}' | '\u{180E}' | '\u{200B}' | '\u{200E}' | '\u{200F}' | '\u{2060}' | '\u{FEFF}'
+        | '\u{202A}'..='\u{202E}' | '\u{2066}'..='\u{2069}'
+    )
+}
+
+fn is_joiner(c: char) -> bool {
+    matches!(c, '\u{200C}' | '\u{200D}')
+}
+
+fn is_outer_junk(c: char) -> bool {
+    c.is_whitespace() || is_joiner(c)
+}
+
+
+/// everything that is wrong with a name (empty if nothing)
+pub fn name_issues(name: &str) -> Vec<NameIssue> {
+    let mut issues = Vec::new();
+    if name.trim() != name {
+        issues.push(NameIssue::OuterWhitespace);
+    }
+    let joiner_at_end = name.trim().starts_with(is_joiner) || name.trim().ends_with(is_joiner);
+    if joiner_at_end || name.chars().any(is_zero_width) {
+        issues.push(NameIssue::ZeroWidth);
+    }
+    if name.chars().any(char::is_control) {
+        issues.push(NameIssue::Control);
+    }
+    if !is_nfc(name) {
+        issues.push(NameIssue::NotNfc);
+    }
+    issues
+}
+
+
+/// removes zero width and control chars (control whitespace like `\t` becomes a space),
+/// converts to NFC and trims whitespace and joiners from the ends
+pub fn clean_name(name: &str) -> String {
+    let cleaned: String = name.chars()
+        .filter(|&c| !is_zero_width(c))
+        .filter_map(|c| match c {
+            c if c.is_ascii_whitespace() => Some(' '),
+            c if c.is_control() => None,
+            c => Some(c),
+        })
+        .nfc()
+        .collect();
+    cleaned.trim_matches(is_outer_junk).to_string()
+}
+
+
+
+
+impl RecipesState {
+    /// every name with a `NameIssue`
+    pub fn check_element_names(&self) -> Vec<NameProblem> {
+        self.num_to_str.iter()
+            .skip(HARDCODED_IDS.len())
+            .filter_map(|name| {
+                let issues = name_issues(name);
+                (!issues.is_empty()).then(|| NameProblem { element: name.clone(), issues, clean: clean_name(name) })
+            })
+            .collect()
+    }
+
+
+    /// renames every bad name to its `clean_name`, or merges it into the element with that name if it exists.
+    /// recipes of merged elements get rewritten to the clean element (and merged like a loaded file would be),
+    /// the merged names stay in `num_to_str` without any recipes (`compact` drops them), their dead evidence,
+    /// explorer depths and requests move to the clean element too.
+    /// renamed elements get a new neal case link (`recanonicalize`).
+    pub fn clean_element_names(&mut self) -> NameHygieneReport {
+        let start_time = Instant::now();
+        let mut report = NameHygieneReport { problems: self.check_element_names(), ..Default::default() };
+
+        let mut str_to_num = self.get_str_to_num_map();
+        let mut remap: Vec<Element> = (0..self.num_to_str.len() as Element).collect();
+        for problem in &report.problems {
+            if problem.clean.is_empty() { continue; }
+            let id = str_to_num[&problem.element];
+
+            if let Some(&into) = str_to_num.get(&problem.clean) {
+                remap[id as usize] = into;
+            } else {
+                str_to_num.remove(&problem.element);
+                str_to_num.insert(problem.clean.clone(), id);
+                self.num_to_str[id as usize] = problem.clean.clone();
+                report.renamed.push((problem.element.clone(), problem.clean.clone()));
+            }
+        }
+
+        // take out every recipe that uses a merged element, then merge them back in with the new ids
+        let is_merged = |e: Element| remap[e as usize] != e;
+        let moved: Vec<((Element, Element), Element)> = self.recipes_ing.iter()
+            .filter(|&(&(f, s), &r)| is_merged(f) || is_merged(s) || is_merged(r))
+            .map(|(&recipe, &r)| (recipe, r))
+            .collect();
+
+        let mut merged_recipes = vec![0; self.num_to_str.len()];
+        let sources: Vec<_> = moved.iter().map(|&(recipe, _)| self.provenance.recipe_source(recipe)).collect();
+        for &((f, s), r) in &moved {
+            self.recipes_ing.remove(&(f, s));
+            if let Some(recipe_sources) = &mut self.provenance.recipe_sources {
+                recipe_sources.remove(&(f, s));
+            }
+            for e in [f, s, r] {
+                if is_merged(e) { merged_recipes[e as usize] += 1; }
+            }
+        }
+
+        let conflicts_before = self.merging.conflict_count;
+        for (((f, s), r), source) in moved.into_iter().zip(sources) {
+            let recipe = sort_recipe_tuple((remap[f as usize], remap[s as usize]));
+            let result = remap[r as usize];
+            let action = self.merging.merge_action(&self.provenance, recipe, self.recipes_ing.get(&recipe).copied(), result, source);
+            self.apply_merge_action(recipe, result, source, action);
+        }
+        report.recipe_conflicts = self.merging.conflict_count - conflicts_before;
+
+        let to_request: Vec<(Element, Element)> = self.to_request_recipes.iter().map(|recipe| *recipe).collect();
+        for (f, s) in to_request {
+            if is_merged(f) || is_merged(s) {
+                self.to_request_recipes.remove(&(f, s));
+                let recipe = sort_recipe_tuple((remap[f as usize], remap[s as usize]));
+                // (the merge might have brought the recipe already)
+                if !self.recipes_ing.contains_key(&recipe) && !self.recipes_ing.contains_key(&(recipe.1, recipe.0)) {
+                    self.to_request_recipes.insert(recipe);
+                }
+            }
+        }
+
+        // dead evidence and explorer depths of merged elements go to the element they got merged into
+        // (the counts add up, it's only as dead as the less dead one. `classify_dead_elements` again for exact numbers)
+        let evidence = &mut self.dead_elements.evidence;
+        for (from, &into) in remap.iter().enumerate() {
+            if from as Element == into || from >= evidence.len() || into as usize >= evidence.len() { continue; }
+            let from_evidence = std::mem::take(&mut evidence[from]);
+            let into_evidence = &mut evidence[into as usize];
+            into_evidence.tested += from_evidence.tested;
+            into_evidence.nothing += from_evidence.nothing;
+            into_evidence.confidence = into_evidence.confidence.min(from_evidence.confidence);
+        }
+        for priority in &mut self.request_priorities {
+            if let RequestPriority::ExplorerDepth(depth_map) = priority {
+                for (e, depth) in std::mem::take(depth_map) {
+                    let min_depth = depth_map.entry(remap[e as usize]).or_insert(depth);
+                    *min_depth = (*min_depth).min(depth);
+                }
+            }
+        }
+
+        report.merges = remap.iter().enumerate()
+            .filter(|&(from, &into)| from as Element != into)
+            .map(|(from, &into)| NameMerge {
+                from: self.num_to_str[from].clone(),
+                into: self.num_to_str[into as usize].clone(),
+                recipes: merged_recipes[from],
+            })
+            .collect();
+
+        if !report.renamed.is_empty() {
+            self.recanonicalize();
+        }
+
+        println!("Cleaned element names in {:?} - bad: {}, renamed: {}, merged: {}, recipe conflicts: {}",
+            start_time.elapsed(),
+            report.problems.len().to_formatted_string(&num_format::Locale::en).yellow(),
+            report.renamed.len().to_formatted_string(&num_format::Locale::en).green(),
+            report.merges.len().to_formatted_string(&num_format::Locale::en).green(),
+            report.recipe_conflicts.to_formatted_string(&num_format::Locale::en).red(),
+        );
+        report
+    }
+
+
+    /// what `load` does after every file (see `NameHygiene`)
+    pub(crate) fn check_names_after_load(&mut self) {
+        match self.name_hygiene {
+            NameHygiene::Ignore => {},
+            NameHygiene::Report => {
+                let problems = self.check_element_names();
+                if !problems.is_empty() {
+                    println!("{}", format!("[!] {} element names have invisible differences (spaces at the ends, zero width, control chars, not NFC), see `clean_element_names`",
+                        problems.len().to_formatted_string(&num_format::Locale::en)).yellow());
+                }
+            },
+            NameHygiene::Clean => {
+                self.clean_element_names();
+            },
+        }
+    }
+}
+
+
+
+
+impl NameHygieneReport {
+    /// writes every bad name, rename and merge into the `RECIPE_FILES_FOLDER` (names are `{:?}` formatted, to see what's wrong with them)
+    pub fn write_to_file(&self, file_name: &str) -> io::Result<()> {
+        let file = File::create(format!("{RECIPE_FILES_FOLDER}/{file_name}"))?;
+        let mut writer = BufWriter::new(file);
+
+        writeln!(writer, "bad names: {}, renamed: {}, merged: {}, recipe conflicts: {}\n",
+            self.problems.len(), self.renamed.len(), self.merges.len(), self.recipe_conflicts)?;
+        for p in &self.problems {
+            writeln!(writer, "{:?}: {:?} -> {:?}", p.element, p.issues, p.clean)?;
+        }
+        if !self.renamed.is_empty() {
+            writeln!(writer, "\nrenamed:")?;
+            for (old, new) in &self.renamed {
+                writeln!(writer, "{old:?} -> {new:?}")?;
+            }
+        }
+        if !self.merges.is_empty() {
+            writeln!(writer, "\nmerged:")?;
+            for m in &self.merges {
+                writeln!(writer, "{:?} -> {:?} ({} recipes)", m.from, m.into, m.recipes)?;
+            }
+        }
+        writer.flush()
+    }
+}
@@ -0,0 +1,172 @@
+//! the neal case normalization: which spelling every case variant of an element maps to (`neal_case_map`).
+//!
+//! `start_case_unicode` is the game case version of a name, char by char:
+//! - ASCII whitespace (`' '`, `\t`, `\n`, `\x0C`, `\r`) ends a word and becomes a single `' '`. runs of whitespace
+//!   are not collapsed and nothing gets trimmed. other whitespace (no-break space, ideographic space, ...) is
+//!   a normal char and doesn't end a word
+//! - the first char of a word gets its full unicode uppercase mapping, like a JS `toUpperCase` of it would
+//!   (`ß` -> `SS`, `ﬁ` -> `FI`, `ŉ` -> `ʼN`)
+//! - every other char gets its full unicode lowercase mapping (`İ` -> `i̇`). this is done char by char,
+//!   so there is no final sigma (`Σ` is always `σ`)
+//! - hyphens, apostrophes, quotes, brackets, digits, emojis, ... don't end a word: `Jack-o-lantern`, `Rock 'n' Roll`,
+//!   `"hello"`, `3d Printer`. they don't have a case, so a word starting with one of them is effectively not capitalized
+//! - scripts without case (CJK, arabic, ...) stay exactly the same. titlecase letters (`ǅ`) aren't special, a first `ǆ` becomes `Ǆ`
+//!
+//! because of the expanding chars it isn't idempotent: `ßa` -> `SSa` -> `Ssa`. `neal_case_map` can't have chains
+//! (every neal case element has to be its own neal case element), so the map uses `neal_case_name`,
+//! which follows `start_case_unicode` until it doesn't change anymore (`ßa`, `SSa`, `Ssa` and `ssa` all end up at `Ssa`).
+//!
+//! `neal_case_map` is never saved, every load rebuilds it, so loading a database is enough to get the current rules.
+//! `RecipesState::recanonicalize` is for states that are already in memory (e.g. after `clean_element_names` renamed elements).
+//! the only saved thing keyed by neal case names is the `DepthIndex`, `DepthIndex::recanonicalize` migrates it.
+//! (lineages files get loaded through `variables_add_element_str`, so they don't need it)
+//!
+//! `tests/case_normalization.rs` has the whole table of cases.
+
+use serde::{Deserialize, Serialize};
+use std::{fs::File, io::{self, BufWriter, Write}, time::Instant};
+use num_format::ToFormattedString;
+use colored::Colorize;
+
+use crate::{RECIPE_FILES_FOLDER, structures::RecipesState};
+
+
+
+/// the game case version of a name, see the module docs for the rules.
+/// (not idempotent, use `neal_case_name` for the `neal_case_map`)
+pub fn start_case_unicode(input: &str) -> String {
+    // ß -> SS etc. can make it grow, but almost every name stays the same length
+    let mut result = String::with_capacity(input.len());
+    let mut capitalize_next = true;
+
+    for c in input.chars() {
+        if is_word_boundary(c) {
+            result.push(' ');
+            capitalize_next = true;
+        } else if capitalize_next {
+            result.extend(c.to_uppercase());
+            capitalize_next = false;
+        } else {
+            result.extend(c.to_lowercase());
+        }
+    }
+    result
+}
+
+
+/// the neal case element of a name: `start_case_unicode` until it doesn't change anymore.
+/// (only expanding first chars need more than one round, and never more than 3)
+pub fn neal_case_name(input: &str) -> String {
+    let mut name = start_case_unicode(input);
+    loop {
+        let next = start_case_unicode(&name);
+        if next == name {
+            return name;
+        }
+        name = next;
+    }
+}
+
+
+/// the only chars that end a word (and the only ones that get replaced)
+pub fn is_word_boundary(c: char) -> bool {
+    c.is_ascii_whitespace()
+}
+
+
+/// if a name already is its own neal case element
+pub fn is_neal_case(name: &str) -> bool {
+    start_case_unicode(name) == name
+}
+
+
+
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NealCaseMove {
+    pub element: String,
+    pub old_neal: String,
+    pub new_neal: String,
+}
+
+
+/// which `neal_case_map` links moved when the map got rebuilt with the current rules
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NealCaseReport {
+    pub moved: Vec<NealCaseMove>,
+    /// neal case elements that didn't exist yet (they get added by `recanonicalize`)
+    pub added: Vec<String>,
+}
+
+
+
+
+impl RecipesState {
+    /// what `recanonicalize` would change, without changing anything
+    pub fn neal_case_changes(&self) -> NealCaseReport {
+        let str_to_num = self.get_str_to_num_map();
+        let mut report = NealCaseReport::default();
+
+        for (id, name) in self.num_to_str.iter().enumerate() {
+            let new_neal = neal_case_name(name);
+            let old_neal = &self.num_to_str[self.neal_case_map[id] as usize];
+            if *old_neal != new_neal {
+                if !str_to_num.contains_key(&new_neal) && !report.added.contains(&new_neal) {
+                    report.added.push(new_neal.clone());
+                }
+                report.moved.push(NealCaseMove { element: name.clone(), old_neal: old_neal.clone(), new_neal });
+            }
+        }
+        report
+    }
+
+
+    /// rebuilds `neal_case_map` with the current `neal_case_name` rules (adding neal case elements that don't exist yet).
+    /// recipes aren't touched, they're stored with the exact case ids anyways.
+    /// (a freshly loaded state never has anything to change, see the module docs)
+    pub fn recanonicalize(&mut self) -> NealCaseReport {
+        let start_time = Instant::now();
+        let report = self.neal_case_changes();
+
+        let mut str_to_num = self.get_str_to_num_map();
+        for name in &report.added {
+            self.variables_add_element_str(name, &mut str_to_num);
+        }
+        for m in &report.moved {
+            let id = str_to_num[&m.element];
+            self.neal_case_map[id as usize] = str_to_num[&m.new_neal];
+        }
+        // the added ones are neal case already, `variables_add_element_str` linked them to themselves
+        debug_assert!(self.neal_case_map.iter().all(|&neal| self.neal_case_map[neal as usize] == neal));
+
+        println!("Recanonicalized neal case map in {:?} - moved: {}, added: {}",
+            start_time.elapsed(),
+            report.moved.len().to_formatted_string(&num_format::Locale::en).yellow(),
+            report.added.len().to_formatted_string(&num_format::Locale::en).green(),
+        );
+        report
+    }
+}
+
+
+
+
+impl NealCaseReport {
+    /// writes every moved link as `Element: Old -> New` into the `RECIPE_FILES_FOLDER`
+    pub fn write_to_file(&self, file_name: &str) -> io::Result<()> {
+        let file = File::create(format!("{RECIPE_FILES_FOLDER}/{file_name}"))?;
+        let mut writer = BufWriter::new(file);
+
+        writeln!(writer, "moved: {}, added: {}\n", self.moved.len(), self.added.len())?;
+        for m in &self.moved {
+            writeln!(writer, "{}: {} -> {}", m.element, m.old_neal, m.new_neal)?;
+        }
+        if !self.added.is_empty() {
+            writeln!(writer, "\nadded:")?;
+            for name in &self.added {
+                writeln!(writer, "{name}")?;
+            }
+        }
+        writer.flush()
+    }
+}
@@ -1,4 +1,4 @@
-use std::{cmp, collections::hash_map, fmt::Write as FmtWrite, hash::Hash, sync::Arc, time::Instant, cell::RefCell, fs::{self, File}, io::{self, BufWriter, Write}, path::PathBuf};
+use std::{cmp, collections::hash_map, fmt::Write as FmtWrite, hash::Hash, sync::Arc, time::Instant, cell::RefCell, fs::{self, File}, io::{self, BufWriter}, path::PathBuf};
 use dashmap::DashSet;
 use rustc_hash::{FxBuildHasher, FxHashMap, FxHashSet};
 use async_recursion::async_recursion;
@@ -7,7 +7,9 @@ use tinyvec::ArrayVec;  // can't move to heap
 use colored::Colorize;
 
 use crate::{DEPTH_EXPLORER_DEPTH_GROW_FACTOR_GUESS, DEPTH_EXPLORER_JUST_MARK_UNKNOWN_NO_REQUESTS_NO_ENCOUNTERED, DEPTH_EXPLORER_MAX_STEPS, LINEAGES_FILE_COOL_JSON_MODE};
+use crate::cancel;
 use crate::structures::{Element, RecipesState, BASE_IDS, sort_recipe_tuple, NOTHING_ID};
+use crate::lineages_file::{LineagesFileEntry, LineagesFileFormat, LineagesFileHeader, LineagesFileWriter, TextLineagesWriter};
 
 
 
@@ -16,6 +18,9 @@ pub trait IsSeed: Send + Sync {
     fn len(&self) -> usize {
         self.as_slice().len()
     }
+    fn is_empty(&self) -> bool {
+        self.as_slice().is_empty()
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
@@ -28,6 +33,7 @@ impl Seed {
         self.elems.insert(insertion_point, element);
     }
     pub fn len(&self) -> usize { self.elems.len() }
+    pub fn is_empty(&self) -> bool { self.elems.is_empty() }
 }
 impl IsSeed for Seed {
     fn as_slice(&self) -> &[Element] { &self.elems }
@@ -74,7 +80,6 @@ struct DepthExplorerPrivateStructures {
     encountered: EncounteredMap,
     
     element_base_cache: ElementBaseCacheMap,
-    num_to_str_len: Box<[usize]>,
 
     start_time: Instant,
 }
@@ -125,7 +130,7 @@ impl RecipesState {
             else { self.depth_explorer_split_start(&new_de_vars).await };
 
             if !DEPTH_EXPLORER_JUST_MARK_UNKNOWN_NO_REQUESTS_NO_ENCOUNTERED {
-                let element_ic = self.neal_case_map[element as usize];
+                let element_ic = self.case_id(element);
                 new_encountered = extend_encountered_seeds(new_encountered, element_ic);
             }
             new_encountered
@@ -136,6 +141,7 @@ impl RecipesState {
         let mut collected_encountereds = initial_split_encountered.clone();
 
         for element in initial_split_encountered.into_keys() /* .collect::<Vec<Element>>().into_iter().rev() */ {
+            if cancel::is_cancelled() { break; }
             let element_encountered = process_element(element, initial_split_de_vars.exclude_depth1_elements.clone()).await;
             if !DEPTH_EXPLORER_JUST_MARK_UNKNOWN_NO_REQUESTS_NO_ENCOUNTERED {
                 collected_encountereds = merge_encountered_maps(collected_encountereds, element_encountered);
@@ -168,7 +174,7 @@ impl RecipesState {
 
     pub async fn depth_explorer_start(&mut self, de_vars: &DepthExplorerVars) -> EncounteredMap {
         let base_lineage_vec: FxHashSet<Element> = BASE_IDS.chain(de_vars.lineage_elements.iter().copied()).collect();
-        let base_lineage_vec_ic: Box<[Element]> = base_lineage_vec.iter().map(|&x| self.neal_case_map[x as usize]).collect();
+        let base_lineage_vec_ic: Box<[Element]> = base_lineage_vec.iter().map(|&x| self.case_id(x)).collect();
         
         // --- Private Structures ---
         let mut de_struc = DepthExplorerPrivateStructures {
@@ -181,7 +187,6 @@ impl RecipesState {
             encountered: FxHashMap::default(),
 
             element_base_cache: Vec::new(),
-            num_to_str_len: Self::get_num_to_str_len(&self.num_to_str),
 
             start_time: Instant::now(),
         };
@@ -194,6 +199,7 @@ impl RecipesState {
             if !self.to_request_recipes.is_empty() {
                 if DEPTH_EXPLORER_JUST_MARK_UNKNOWN_NO_REQUESTS_NO_ENCOUNTERED { self.mark_all_to_request_recipes_unknown(); }
                 else { self.process_all_to_request_recipes("Depth 1").await; }
+                if cancel::is_cancelled() { return de_struc.encountered; }
                 continue;
             }
         
@@ -205,8 +211,8 @@ impl RecipesState {
 
             let depth1_ic: Vec<Element> = depth1
                 .into_iter()
-                .map(|x| self.neal_case_map[x as usize])
-                .filter(|&x| de_struc.num_to_str_len[x as usize] <= 30)
+                .map(|x| self.case_id(x))
+                .filter(|&x| !self.is_dead_element(x))
                 .collect();
 
             de_struc.base_lineage_depth1.extend(depth1_ic.iter());
@@ -227,7 +233,7 @@ impl RecipesState {
 
         
         // --- Main Loop ---
-        while de_struc.depth < de_vars.stop_after_depth {
+        while de_struc.depth < de_vars.stop_after_depth && !cancel::is_cancelled() {
 
             // --- do all Element - Base combinations and cache them ---
             self.cache_all_element_base_results(&mut de_struc);
@@ -280,6 +286,8 @@ impl RecipesState {
                     break;
                 }
                 else { self.process_all_to_request_recipes(&format!("Depth {}", de_struc.depth + 1)).await; }
+                // the paused depth can't be finished anymore, return what we have
+                if cancel::is_cancelled() { break; }
             }
         }
 
@@ -352,7 +360,7 @@ impl RecipesState {
             for &result in &all_results {
                 add_to_local_encountered(result, seed, local_encountered, &de_struc.encountered);
 
-                if de_struc.num_to_str_len[result as usize] > 30 { continue; }
+                if self.is_dead_element(result) { continue; }
             
 
                 // eliminate seeds with too many depth1s
@@ -362,7 +370,7 @@ impl RecipesState {
                 // = (3*count_depth1s - 2*depth <= 4)
 
                 // (count_depth1s + if depth1.contains(&result) {1} else {0}) = total depth1s in the full seed.
-                let result_ic = self.neal_case_map[result as usize];
+                let result_ic = self.case_id(result);
 
                 if 3*(count_depth1s + (if de_struc.depth1.contains(&result_ic) {1} else {0})) - 2*(de_struc.depth as i32) <= 4
                     && !seed.elems.contains(&result_ic) {
@@ -450,8 +458,8 @@ impl RecipesState {
 
 
         for (&element, seeds) in &de_struc.encountered {
-            if de_struc.num_to_str_len[element as usize] > 30 || seeds.first().unwrap().len() >= de_struc.depth { continue; }
-            let neal_element = self.neal_case_map[element as usize];
+            if self.is_dead_element(element) || seeds.first().unwrap().len() >= de_struc.depth { continue; }
+            let neal_element = self.case_id(element);
 
             if de_struc.element_base_cache[neal_element as usize].is_none() {
                 // cache results
@@ -489,19 +497,15 @@ impl RecipesState {
 
 
 
-    pub fn get_encountered_entry<S: IsSeed>(
+    /// every lineage (one per seed) of an encountered element, as `[first, second, result]` steps
+    pub fn get_encountered_lineages<S: IsSeed>(
         &self,
         element: Element,
         seeds: &[S],
         initial_crafted: &FxHashSet<Element>,
         real_recipes_result: &RealRecipesResult
-    ) -> String {
-
-        let mut message = String::with_capacity(seeds.len() * seeds[0].len() * 7);
-        if LINEAGES_FILE_COOL_JSON_MODE { write!(message, "{}: [", serde_json::to_string(&self.num_to_str_fn(element)).unwrap()).unwrap(); }
-        else { write!(message, "{} - {}:", seeds[0].len() + 1, self.num_to_str_fn(element)).unwrap(); }
-
-        for (i, seed) in seeds.iter().enumerate() {        
+    ) -> Vec<Vec<[Element; 3]>> {
+        seeds.iter().map(|seed| {
 
             let mut lineage: Vec<[Element; 3]> = Vec::with_capacity(seed.len() + 1);
             let mut to_craft: Vec<Element> = seed.as_slice().to_vec();
@@ -550,8 +554,25 @@ impl RecipesState {
                 *caps_map.get(&final_recipe.1).unwrap_or(&final_recipe.1),
                 element
             ]);
+            lineage
+        }).collect()
+    }
+
+
+
+    pub fn get_encountered_entry<S: IsSeed>(
+        &self,
+        element: Element,
+        seeds: &[S],
+        initial_crafted: &FxHashSet<Element>,
+        real_recipes_result: &RealRecipesResult
+    ) -> String {
 
+        let mut message = String::with_capacity(seeds.len() * seeds[0].len() * 7);
+        if LINEAGES_FILE_COOL_JSON_MODE { write!(message, "{}: [", serde_json::to_string(&self.num_to_str_fn(element)).unwrap()).unwrap(); }
+        else { write!(message, "{} - {}:", seeds[0].len() + 1, self.num_to_str_fn(element)).unwrap(); }
 
+        for (i, lineage) in self.get_encountered_lineages(element, seeds, initial_crafted, real_recipes_result).into_iter().enumerate() {
             let lineage_string = if LINEAGES_FILE_COOL_JSON_MODE { self.format_lineage_json_no_goals(&lineage) }
             else { self.format_lineage_no_goals(&lineage) };
 
@@ -575,8 +596,15 @@ impl RecipesState {
 
 
 
+    /// writes `Lineages Files/{last lineage element} Seed - {max_depth} Steps.json` (or `.txt`, see `LINEAGES_FILE_COOL_JSON_MODE`)
     pub fn generate_lineages_file<S: IsSeed>(
         &self, lineage_elements: &[Element], max_depth: usize, encountered: &FxHashMap<Element, Vec<S>>
+    ) -> io::Result<()> {
+        self.generate_lineages_file_with_format(lineage_elements, max_depth, encountered, LineagesFileFormat::default())
+    }
+
+    pub fn generate_lineages_file_with_format<S: IsSeed>(
+        &self, lineage_elements: &[Element], max_depth: usize, encountered: &FxHashMap<Element, Vec<S>>, format: LineagesFileFormat
     ) -> io::Result<()> {
         // required for this function:
         let start_time = Instant::now();
@@ -586,9 +614,9 @@ impl RecipesState {
         );
 
         for (&(first, second), &result) in &self.recipes_ing {
-            let f = self.neal_case_map[first as usize];
-            let s = self.neal_case_map[second as usize];
-            let r = self.neal_case_map[result as usize];
+            let f = self.case_id(first);
+            let s = self.case_id(second);
+            let r = self.case_id(result);
             if result == r {
                 // if result is neal case
                 real_recipes_result.entry(r).or_default().push((f, s, None));
@@ -612,7 +640,7 @@ impl RecipesState {
         let file_name = format!("{} Seed - {} Steps.{}",
             &self.num_to_str_fn(*lineage_elements.last().unwrap()),
             max_depth,
-            if LINEAGES_FILE_COOL_JSON_MODE {"json"} else {"txt"}
+            format.extension()
         );
 
         let folder_path = PathBuf::from(folder_name);
@@ -621,16 +649,22 @@ impl RecipesState {
         let full_path = folder_path.join(file_name);
 
         let file = File::create(full_path)?;
-        let mut writer = BufWriter::new(file);
+        let writer = BufWriter::new(file);
 
         // --- Parallel Processing ---
-        let mut keyed_entries: Vec<(usize, u32, String)> = encountered
+        let mut keyed_entries: Vec<(usize, u32, LineagesFileEntry)> = encountered
             .par_iter()
             .map(|(&element, seeds)| {
 
                 let seed_len = seeds.first().unwrap().len();
-                let formatted_string = self.get_encountered_entry(element, seeds, &initial_crafted, &real_recipes_result);
-                (seed_len, element, formatted_string)
+                let lineages = self.get_encountered_lineages(element, seeds, &initial_crafted, &real_recipes_result);
+                let entry = LineagesFileEntry {
+                    element: self.num_to_str_fn(element),
+                    lineages: lineages.into_iter()
+                        .map(|lineage| lineage.into_iter().map(|step| step.map(|e| self.num_to_str_fn(e))).collect())
+                        .collect(),
+                };
+                (seed_len, element, entry)
             })
             .collect();
 
@@ -647,61 +681,29 @@ impl RecipesState {
 
 
         // --- Writing ---
-        if LINEAGES_FILE_COOL_JSON_MODE {
-            writeln!(writer, "{{")?;
-            writeln!(writer, "\"elements_ran\": [{}],\n", lineage_elements_str.iter().map(|x| serde_json::to_string(x).unwrap()).collect::<Vec<_>>().join(", "))?;
-
-            writeln!(writer, "\"element_count_stats\": {{").unwrap();
-            for (i, count) in elements_per_depth_count.into_iter().enumerate() {
-                writeln!(writer, "    \"depth_{}\": {},", i + 1, count)?;
-            }
-            writeln!(writer, "    \"total\": {}", encountered.len())?;
-            writeln!(writer, "}},\n\n\n").unwrap();
-
-            writeln!(writer, "\"elements\": {{").unwrap();
-            let mut iter = keyed_entries.iter().peekable();
-            while let Some((_, _, entry_string)) = iter.next() {
-                writer.write_all(entry_string.as_bytes())?;
-                if iter.peek().is_some() { write!(writer, ",\n\n").unwrap();  }
-                else { write!(writer, "\n\n").unwrap();  }
-            }
-            writeln!(writer, "}}").unwrap();
-
-
-            
-            write!(writer, "}}")?;
-        }
-        else {
-            writeln!(writer, "TODO...  // {}\n\n\n", lineage_elements.len() - 4)?;
-
-            for (i, count) in elements_per_depth_count.into_iter().enumerate() {
-                writeln!(writer, "{} Steps - {} Elements", i + 1, count)?;
-            }
-            writeln!(writer, "Total Elements: {}\n\n\n\n", encountered.len())?;
-
-
-
-            for (_, _, entry_string) in &keyed_entries {
-                writer.write_all(entry_string.as_bytes())?;
-            }
-            // --- JSON Generation ---
-            let json_map: FxHashMap<String, usize> = keyed_entries
-                .into_iter()
-                .map(|(seed_len, element, _formatted_string)| {
-                    (self.num_to_str_fn(element), seed_len + 1)
-                })
-                .collect();
-
-            let json_string = serde_json::to_string_pretty(&json_map).expect("JSON seriliaziation failed...");
-
-            writer.write_all(b"\n")?;
-            writer.write_all(json_string.as_bytes())?;
-            writer.write_all(b"\n")?; 
+        let header = LineagesFileHeader {
+            elements_ran: lineage_elements_str,
+            case_mode: self.case_mode,
+            depth_counts: elements_per_depth_count,
+            total: encountered.len(),
+        };
+        match format {
+            LineagesFileFormat::Json => {
+                let mut lineages_writer = LineagesFileWriter::new(writer, &header)?;
+                for (_, _, entry) in &keyed_entries {
+                    lineages_writer.write_entry(entry)?;
+                }
+                lineages_writer.finish()?;
+            },
+            LineagesFileFormat::Text(options) => {
+                let mut lineages_writer = TextLineagesWriter::new(writer, &header, options)?;
+                for (_, _, entry) in &keyed_entries {
+                    lineages_writer.write_entry(entry)?;
+                }
+                lineages_writer.finish()?;
+            },
         }
 
-
-
-
         println!("Generated Lineages File: {:?}", start_time.elapsed());
 
         Ok(())
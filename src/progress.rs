@@ -0,0 +1,316 @@
+use std::{collections::BTreeMap, fmt::Write as FmtWrite, fs::{self, File, OpenOptions}, io::{self, BufWriter, Write}, sync::{Arc, Mutex, atomic::{AtomicBool, AtomicUsize, Ordering}}, thread, time::{Duration, Instant, SystemTime, UNIX_EPOCH}};
+use num_format::ToFormattedString;
+use serde::Serialize;
+use colored::Colorize;
+
+
+
+/// upper bounds of the requests/sec histogram buckets (the last bucket is everything above)
+const RATE_HISTOGRAM_BUCKETS: &[f64] = &[1.0, 10.0, 50.0, 100.0, 200.0, 500.0, 1000.0];
+
+
+/// where progress snapshots get written to (on top of the terminal).
+/// paths are used as-is, so relative paths are relative to the working directory of the process
+/// (not the `RECIPE_FILES_FOLDER`).
+#[derive(Debug, Clone, Default)]
+pub enum MetricsExport {
+    #[default]
+    TerminalOnly,
+    /// appends one JSON object per snapshot, easy to graph a run afterwards
+    JsonLines(String),
+    /// rewrites a Prometheus text-format file with the latest snapshot of every job
+    /// (e.g. for the node_exporter textfile collector)
+    Prometheus(String),
+}
+
+static METRICS_EXPORT: Mutex<MetricsExport> = Mutex::new(MetricsExport::TerminalOnly);
+/// the latest snapshot of every job, the prometheus file always contains all of them
+static LATEST_SNAPSHOTS: Mutex<BTreeMap<String, ProgressSnapshot>> = Mutex::new(BTreeMap::new());
+
+
+pub fn set_metrics_export(export: MetricsExport) {
+    *METRICS_EXPORT.lock().expect("METRICS_EXPORT lock poisoned") = export;
+}
+
+
+
+
+/// progress of one long running job. (a request batch, a layer explorer run, a load...)
+/// it is shared with `Arc` and all counters are atomic, so rayon threads can just count on it.
+#[derive(Debug)]
+pub struct Progress {
+    name: String,
+    start_time: Instant,
+    done: AtomicUsize,
+    total: AtomicUsize,
+    counters: Mutex<BTreeMap<String, u64>>,
+    rate_samples: Mutex<RateSamples>,
+}
+
+#[derive(Debug)]
+struct RateSamples {
+    last_time: Instant,
+    last_done: usize,
+    histogram: Vec<u64>,
+    sum: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProgressSnapshot {
+    pub job: String,
+    pub timestamp_ms: u128,
+    pub elapsed_secs: f64,
+    pub done: usize,
+    pub total: usize,
+    pub rate_per_sec: f64,
+    pub eta_secs: Option<f64>,
+    pub memory_rss_bytes: Option<u64>,
+    pub counters: BTreeMap<String, u64>,
+    /// `(upper bound, how many snapshots had a rate <= that bound)`, the last bound is infinity
+    pub rate_histogram: Vec<(f64, u64)>,
+    pub rate_histogram_sum: f64,
+}
+
+
+impl Progress {
+    pub fn new(name: &str, total: usize) -> Arc<Self> {
+        Arc::new(Self {
+            name: name.to_string(),
+            start_time: Instant::now(),
+            done: AtomicUsize::new(0),
+            total: AtomicUsize::new(total),
+            counters: Mutex::new(BTreeMap::new()),
+            rate_samples: Mutex::new(RateSamples {
+                last_time: Instant::now(),
+                last_done: 0,
+                histogram: vec![0; RATE_HISTOGRAM_BUCKETS.len() + 1],
+                sum: 0.0,
+            }),
+        })
+    }
+
+    pub fn inc(&self, amount: usize) {
+        self.done.fetch_add(amount, Ordering::Relaxed);
+    }
+    pub fn done(&self) -> usize {
+        self.done.load(Ordering::Relaxed)
+    }
+    pub fn set_total(&self, total: usize) {
+        self.total.store(total, Ordering::Relaxed);
+    }
+
+    /// named counters, e.g. `depth_3` -> elements found at depth 3
+    pub fn set_counter(&self, name: &str, value: u64) {
+        self.counters.lock().expect("counters lock poisoned").insert(name.to_string(), value);
+    }
+    pub fn add_counter(&self, name: &str, value: u64) {
+        *self.counters.lock().expect("counters lock poisoned").entry(name.to_string()).or_default() += value;
+    }
+
+
+    /// also takes a requests/sec sample for the histogram (rate since the last snapshot)
+    pub fn snapshot(&self) -> ProgressSnapshot {
+        let done = self.done();
+        let total = self.total.load(Ordering::Relaxed);
+        let elapsed = self.start_time.elapsed().as_secs_f64();
+        let rate = if elapsed > 0.0 { done as f64 / elapsed } else { 0.0 };
+
+        let mut samples = self.rate_samples.lock().expect("rate_samples lock poisoned");
+        let sample_secs = samples.last_time.elapsed().as_secs_f64();
+        if sample_secs > 0.0 {
+            let sample_rate = (done - samples.last_done.min(done)) as f64 / sample_secs;
+            let bucket = RATE_HISTOGRAM_BUCKETS.iter().position(|&b| sample_rate <= b).unwrap_or(RATE_HISTOGRAM_BUCKETS.len());
+            samples.histogram[bucket] += 1;
+            samples.sum += sample_rate;
+            samples.last_time = Instant::now();
+            samples.last_done = done;
+        }
+
+        ProgressSnapshot {
+            job: self.name.clone(),
+            timestamp_ms: SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis()),
+            elapsed_secs: elapsed,
+            done,
+            total,
+            rate_per_sec: rate,
+            eta_secs: (rate > 0.0 && total >= done).then(|| (total - done) as f64 / rate),
+            memory_rss_bytes: memory_rss_bytes(),
+            counters: self.counters.lock().expect("counters lock poisoned").clone(),
+            rate_histogram: RATE_HISTOGRAM_BUCKETS.iter().copied()
+                .chain([f64::INFINITY])
+                .zip(samples.histogram.iter().copied())
+                .collect(),
+            rate_histogram_sum: samples.sum,
+        }
+    }
+
+
+    /// prints a progress line and writes the snapshot to the `MetricsExport`
+    pub fn report(&self) {
+        let snapshot = self.snapshot();
+        println!("{}", snapshot.terminal_line());
+        export_snapshot(snapshot);
+    }
+
+    /// only writes the snapshot to the `MetricsExport`, for jobs that already print their own messages
+    pub fn export(&self) -> ProgressSnapshot {
+        let snapshot = self.snapshot();
+        export_snapshot(snapshot.clone());
+        snapshot
+    }
+
+    /// reports every `every` on its own thread (works inside rayon and tokio).
+    /// the reporter stops when the returned handle gets dropped, with one last report.
+    pub fn spawn_reporter(self: &Arc<Self>, every: Duration) -> ProgressReporter {
+        let stop = Arc::new(AtomicBool::new(false));
+        let progress = Arc::clone(self);
+        let thread_stop = Arc::clone(&stop);
+
+        let handle = thread::spawn(move || {
+            let mut last_report = Instant::now();
+            while !thread_stop.load(Ordering::Relaxed) {
+                thread::sleep(Duration::from_millis(200));
+                if last_report.elapsed() >= every {
+                    progress.report();
+                    last_report = Instant::now();
+                }
+            }
+        });
+        ProgressReporter { progress: Arc::clone(self), stop, handle: Some(handle) }
+    }
+}
+
+
+
+pub struct ProgressReporter {
+    progress: Arc<Progress>,
+    stop: Arc<AtomicBool>,
+    handle: Option<thread::JoinHandle<()>>,
+}
+
+impl Drop for ProgressReporter {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+        self.progress.report();
+    }
+}
+
+
+
+
+impl ProgressSnapshot {
+    pub fn terminal_line(&self) -> String {
+        let mut line = format!("{} Progress: {}/{} ({:.1}%),  Time: {},  Rate: {}/s,  ETA: {}",
+            self.job,
+            self.done.to_formatted_string(&num_format::Locale::en).green(),
+            self.total.to_formatted_string(&num_format::Locale::en).green(),
+            if self.total == 0 { 100.0 } else { self.done as f64 * 100.0 / self.total as f64 },
+            format!("{:?}", Duration::from_secs_f64(self.elapsed_secs)).green(),
+            format!("{:.3}", self.rate_per_sec).green(),
+            self.eta_secs.map_or_else(|| "?".to_string(), |eta| format!("{:?}", Duration::from_secs(eta as u64))).yellow(),
+        );
+        if let Some(rss) = self.memory_rss_bytes {
+            write!(line, ",  Memory: {}MB", (rss / 1024 / 1024).to_formatted_string(&num_format::Locale::en).purple()).unwrap();
+        }
+        for (name, value) in &self.counters {
+            write!(line, ",  {name}: {}", value.to_formatted_string(&num_format::Locale::en).purple()).unwrap();
+        }
+        line
+    }
+}
+
+
+
+fn export_snapshot(snapshot: ProgressSnapshot) {
+    write_snapshot(snapshot).unwrap_or_else(|e| eprintln!("could not export metrics... {e}"));
+}
+
+fn write_snapshot(snapshot: ProgressSnapshot) -> io::Result<()> {
+    let export = METRICS_EXPORT.lock().expect("METRICS_EXPORT lock poisoned").clone();
+
+    match export {
+        MetricsExport::TerminalOnly => Ok(()),
+        MetricsExport::JsonLines(path) => {
+            let file = OpenOptions::new().create(true).append(true).open(path)?;
+            let mut writer = BufWriter::new(file);
+            serde_json::to_writer(&mut writer, &snapshot)?;
+            writeln!(writer)?;
+            writer.flush()
+        },
+        MetricsExport::Prometheus(path) => {
+            let mut latest = LATEST_SNAPSHOTS.lock().expect("LATEST_SNAPSHOTS lock poisoned");
+            latest.insert(snapshot.job.clone(), snapshot);
+
+            // write to a temp file first, so a scraper never sees a half written file
+            let temp_path = format!("{path}.tmp");
+            let mut writer = BufWriter::new(File::create(&temp_path)?);
+            writer.write_all(prometheus_text(&latest).as_bytes())?;
+            writer.flush()?;
+            drop(writer);
+            fs::rename(temp_path, path)
+        },
+    }
+}
+
+
+type SnapshotGauge = (&'static str, fn(&ProgressSnapshot) -> Option<f64>);
+
+fn prometheus_text(snapshots: &BTreeMap<String, ProgressSnapshot>) -> String {
+    let mut out = String::new();
+    let escape = |s: &str| s.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n");
+
+    let gauges: [SnapshotGauge; 6] = [
+        ("icraft_progress_done", |s| Some(s.done as f64)),
+        ("icraft_progress_total", |s| Some(s.total as f64)),
+        ("icraft_progress_elapsed_seconds", |s| Some(s.elapsed_secs)),
+        ("icraft_progress_rate_per_second", |s| Some(s.rate_per_sec)),
+        ("icraft_progress_eta_seconds", |s| s.eta_secs),
+        ("icraft_memory_rss_bytes", |s| s.memory_rss_bytes.map(|x| x as f64)),
+    ];
+    for (metric, value) in gauges {
+        writeln!(out, "# TYPE {metric} gauge").unwrap();
+        for s in snapshots.values() {
+            if let Some(v) = value(s) {
+                writeln!(out, "{metric}{{job=\"{}\"}} {v}", escape(&s.job)).unwrap();
+            }
+        }
+    }
+
+    writeln!(out, "# TYPE icraft_progress_counter gauge").unwrap();
+    for s in snapshots.values() {
+        for (name, value) in &s.counters {
+            writeln!(out, "icraft_progress_counter{{job=\"{}\",name=\"{}\"}} {value}", escape(&s.job), escape(name)).unwrap();
+        }
+    }
+
+    writeln!(out, "# TYPE icraft_rate_per_second histogram").unwrap();
+    for s in snapshots.values() {
+        let mut cumulative = 0;
+        for &(bound, count) in &s.rate_histogram {
+            cumulative += count;
+            let le = if bound.is_infinite() { "+Inf".to_string() } else { bound.to_string() };
+            writeln!(out, "icraft_rate_per_second_bucket{{job=\"{}\",le=\"{le}\"}} {cumulative}", escape(&s.job)).unwrap();
+        }
+        writeln!(out, "icraft_rate_per_second_sum{{job=\"{}\"}} {}", escape(&s.job), s.rate_histogram_sum).unwrap();
+        writeln!(out, "icraft_rate_per_second_count{{job=\"{}\"}} {cumulative}", escape(&s.job)).unwrap();
+    }
+    out
+}
+
+
+
+/// resident memory of this process. (only on linux, `None` everywhere else)
+pub fn memory_rss_bytes() -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    let kb = status.lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))?
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+    Some(kb * 1024)
+}
@@ -0,0 +1,168 @@
+use serde::{Deserialize, Serialize};
+use std::{fs, time::{SystemTime, UNIX_EPOCH}};
+use rustc_hash::FxHashMap;
+use colored::Colorize;
+
+use crate::{RECIPE_FILES_FOLDER, structures::{Element, RecipesState}};
+
+
+
+/// `RecipeSource::name` of recipes that came from a request instead of a file
+pub const REQUESTED_SOURCE: &str = "requested";
+
+/// the source of all recipes that were there before provenance got tracked
+const UNTRACKED_SOURCE: &str = "(untracked)";
+
+pub type SourceId = u16;
+
+
+/// where a recipe came from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecipeSource {
+    /// file name, or `REQUESTED_SOURCE`
+    pub name: String,
+    /// only for requested recipes: the proxy that answered
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+    /// milliseconds since UNIX epoch.
+    /// files: modification time, requests: when the request session started
+    pub timestamp: u128,
+}
+
+
+/// a small table of sources, and (optionally) the `SourceId` for every recipe.
+///
+/// the per-recipe index costs a bit of memory for every recipe, so it is only there
+/// after `track_provenance` (or `set_merge_policy`). it gets saved in `JSONRecipesNum` files.
+#[derive(Debug, Clone, Default)]
+pub struct Provenance {
+    /// every source that recipes came from, a `SourceId` is an index into this
+    pub sources: Vec<RecipeSource>,
+    pub recipe_sources: Option<FxHashMap<(Element, Element), SourceId>>,
+    /// the file that is loading right now
+    pub(crate) loading_source: Option<SourceId>,
+}
+
+
+pub fn now_millis() -> u128 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis())
+}
+
+
+impl Provenance {
+    pub fn is_tracking(&self) -> bool {
+        self.recipe_sources.is_some()
+    }
+
+    /// returns the id of an equal source if there is one already
+    pub(crate) fn add_source(&mut self, source: RecipeSource) -> SourceId {
+        if let Some(id) = self.sources.iter().position(|s| *s == source) {
+            return id as SourceId;
+        }
+        self.sources.push(source);
+        SourceId::try_from(self.sources.len() - 1).expect("more than 65536 recipe sources")
+    }
+
+    /// a file in the `RECIPE_FILES_FOLDER` is about to get loaded
+    pub(crate) fn begin_file_source(&mut self, file_name: &str) -> SourceId {
+        let timestamp = fs::metadata(format!("{RECIPE_FILES_FOLDER}/{file_name}"))
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |d| d.as_millis());
+
+        let id = self.add_source(RecipeSource { name: file_name.to_string(), proxy: None, timestamp });
+        self.loading_source = Some(id);
+        id
+    }
+
+    /// `proxy` is `None` if no proxy answered (the recipe got stored as `Nothing`)
+    pub(crate) fn requested_source(&mut self, proxy: Option<&str>, session_timestamp: u128) -> SourceId {
+        self.add_source(RecipeSource { name: REQUESTED_SOURCE.to_string(), proxy: proxy.map(str::to_string), timestamp: session_timestamp })
+    }
+
+
+    pub fn source(&self, source: Option<SourceId>) -> Option<&RecipeSource> {
+        source.and_then(|s| self.sources.get(s as usize))
+    }
+
+    pub fn source_name(&self, source: Option<SourceId>) -> &str {
+        self.source(source).map_or(UNTRACKED_SOURCE, |s| s.name.as_str())
+    }
+
+    /// (looks up both orders, not every code path inserts recipes sorted)
+    pub fn recipe_source(&self, (first, second): (Element, Element)) -> Option<SourceId> {
+        let recipe_sources = self.recipe_sources.as_ref()?;
+        recipe_sources.get(&(first, second))
+            .or_else(|| recipe_sources.get(&(second, first)))
+            .copied()
+    }
+
+    pub(crate) fn set_recipe_source(&mut self, recipe: (Element, Element), source: SourceId) {
+        if let Some(recipe_sources) = &mut self.recipe_sources {
+            recipe_sources.insert(recipe, source);
+        }
+    }
+
+    /// marks a recipe that just got added with `variables_add_recipe` as requested
+    pub(crate) fn set_requested(
+        &mut self, first_str: &str, second_str: &str, str_to_num: &FxHashMap<String, Element>, proxy: Option<&str>, session_timestamp: u128
+    ) {
+        if !self.is_tracking() { return; }
+        if let (Some(&f), Some(&s)) = (str_to_num.get(first_str), str_to_num.get(second_str)) {
+            let source = self.requested_source(proxy, session_timestamp);
+            self.set_recipe_source((f, s), source);
+        }
+    }
+}
+
+
+
+
+impl RecipesState {
+    /// starts remembering where every recipe comes from. call this before loading anything,
+    /// recipes that are already there have no known source.
+    pub fn track_provenance(&mut self) {
+        if self.provenance.recipe_sources.is_none() {
+            self.provenance.recipe_sources = Some(FxHashMap::default());
+        }
+    }
+
+    pub fn recipe_provenance(&self, first: Element, second: Element) -> Option<&RecipeSource> {
+        self.provenance.source(self.provenance.recipe_source((first, second)))
+    }
+
+    /// all recipes from a source, e.g. `recipes_from_source("scorpia fill.ic")` or `recipes_from_source(REQUESTED_SOURCE)`
+    pub fn recipes_from_source(&self, source_name: &str) -> Vec<(Element, Element)> {
+        let Some(recipe_sources) = &self.provenance.recipe_sources else {
+            println!("{}", "[!] provenance is not tracked, call `track_provenance` before loading".yellow());
+            return Vec::new();
+        };
+        let mut recipes: Vec<(Element, Element)> = recipe_sources.iter()
+            .filter(|&(recipe, &source)| {
+                self.provenance.source_name(Some(source)) == source_name && self.recipes_ing.contains_key(recipe)
+            })
+            .map(|(&recipe, _)| recipe)
+            .collect();
+        recipes.sort_unstable();
+        recipes
+    }
+
+    /// prints how many of the current recipes came from each source
+    pub fn print_provenance_summary(&self) {
+        let mut counts: FxHashMap<Option<SourceId>, usize> = FxHashMap::default();
+        for &recipe in self.recipes_ing.keys() {
+            *counts.entry(self.provenance.recipe_source(recipe)).or_default() += 1;
+        }
+        let mut counts: Vec<_> = counts.into_iter().collect();
+        counts.sort_unstable_by_key(|&(source, count)| (std::cmp::Reverse(count), source));
+
+        println!("Recipe provenance ({}):", self.pretty_element_and_recipe_count());
+        for (source, count) in counts {
+            let proxy = self.provenance.source(source)
+                .and_then(|s| s.proxy.as_deref())
+                .map_or(String::new(), |p| format!(" via {p}"));
+            println!("  - {}{}: {}", self.provenance.source_name(source), proxy, count.to_string().purple());
+        }
+    }
+}
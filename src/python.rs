@@ -0,0 +1,259 @@
+//! python bindings (only with `--features python`).
+//!
+//! ```python
+//! import numpy as np
+//! import infinite_craft_shenanigance as ics
+//!
+//! state = ics.RecipesState()
+//! state.load("full_db.ic")
+//! recipes = np.frombuffer(state.recipes_array(), dtype="<u4").reshape(-1, 3)  # first, second, result
+//! ```
+//! big results (recipes, depths, dead elements) are returned as `bytes` of little-endian `u32`s,
+//! so numpy can use them without copying or parsing anything.
+
+use std::io;
+use pyo3::{exceptions::{PyIndexError, PyKeyError, PyValueError}, prelude::*, types::PyBytes};
+use rustc_hash::FxHashMap;
+
+use crate::{
+    element_search::SearchMode,
+    layer_explorer::LayerExplorer,
+    recipe_loader::RecipesFile,
+    structures::{Element, RecipesState},
+};
+
+
+
+fn u32_bytes<'py>(py: Python<'py>, values: &[u32]) -> Bound<'py, PyBytes> {
+    let bytes: Vec<u8> = values.iter().flat_map(|x| x.to_le_bytes()).collect();
+    PyBytes::new(py, &bytes)
+}
+
+/// `None` picks the format from the file extension (`.ic` or `JSONRecipesNum`)
+fn parse_file_mode(file_name: &str, file_mode: Option<&str>) -> PyResult<RecipesFile> {
+    match file_mode {
+        None if file_name.ends_with(".ic") => Ok(RecipesFile::ICSaveFile),
+        None => Ok(RecipesFile::JSONRecipesNum),
+        Some("ic" | "ICSaveFile") => Ok(RecipesFile::ICSaveFile),
+        Some("json" | "JSONRecipesNum") => Ok(RecipesFile::JSONRecipesNum),
+        Some("old_json" | "JSONOldDepthExplorerRecipes") => Ok(RecipesFile::JSONOldDepthExplorerRecipes),
+        Some(other) => Err(PyValueError::new_err(format!("unknown file mode '{other}' (ic, json or old_json)"))),
+    }
+}
+
+
+/// broken files are a `ValueError`, everything else (missing file, ...) the matching `OSError`
+fn load_error(e: io::Error) -> PyErr {
+    match e.kind() {
+        io::ErrorKind::InvalidData | io::ErrorKind::UnexpectedEof => PyValueError::new_err(e.to_string()),
+        _ => e.into(),
+    }
+}
+
+
+/// all recipes. files are loaded from / saved to the `RECIPE_FILES_FOLDER`, like in rust.
+#[pyclass(name = "RecipesState")]
+struct PyRecipesState {
+    state: RecipesState,
+    /// `str_to_num_fn` is a linear search, way too slow for lookups from python
+    str_to_num: FxHashMap<String, Element>,
+}
+
+impl PyRecipesState {
+    fn id(&self, name: &str) -> PyResult<Element> {
+        self.str_to_num.get(name).copied()
+            .ok_or_else(|| PyKeyError::new_err(format!("'{name}' does not exist... did you mean: {:?}", self.state.did_you_mean(name))))
+    }
+
+    fn name(&self, id: Element) -> PyResult<&str> {
+        self.state.num_to_str.get(id as usize)
+            .map(String::as_str)
+            .ok_or_else(|| PyIndexError::new_err(format!("no element with id {id}")))
+    }
+}
+
+
+#[pymethods]
+impl PyRecipesState {
+    #[new]
+    fn new() -> Self {
+        let state = RecipesState::without_autosave();
+        let str_to_num = state.get_str_to_num_map();
+        Self { state, str_to_num }
+    }
+
+    /// loads (and merges) a recipe file. `file_mode`: "ic", "json" or "old_json"
+    #[pyo3(signature = (file_name, file_mode=None))]
+    fn load(&mut self, py: Python<'_>, file_name: &str, file_mode: Option<&str>) -> PyResult<()> {
+        let format = parse_file_mode(file_name, file_mode)?;
+        let state = &mut self.state;
+        py.detach(|| state.load(file_name, format)).map_err(load_error)?;
+        self.str_to_num = self.state.get_str_to_num_map();
+        Ok(())
+    }
+
+    #[pyo3(signature = (file_name, file_mode=None))]
+    fn save(&self, py: Python<'_>, file_name: &str, file_mode: Option<&str>) -> PyResult<()> {
+        let format = parse_file_mode(file_name, file_mode)?;
+        let state = &self.state;
+        py.detach(|| state.save(file_name, format))?;
+        Ok(())
+    }
+
+
+    fn element_count(&self) -> usize {
+        self.state.num_to_str.len()
+    }
+
+    fn recipe_count(&self) -> usize {
+        self.state.recipes_ing.len()
+    }
+
+    /// raises `KeyError` if the element doesn't exist
+    fn element_id(&self, name: &str) -> PyResult<Element> {
+        self.id(name)
+    }
+
+    fn element_name(&self, id: Element) -> PyResult<String> {
+        self.name(id).map(str::to_string)
+    }
+
+    /// all element names, the index is the element id
+    fn element_names(&self) -> Vec<String> {
+        self.state.num_to_str.clone()
+    }
+
+    /// ranked `(id, name, score)` candidates, `mode`: "substring", "prefix", "regex" or "fuzzy"
+    #[pyo3(signature = (query, mode="substring", case_insensitive=true, max_distance=2, limit=20))]
+    fn search(&self, query: &str, mode: &str, case_insensitive: bool, max_distance: usize, limit: usize) -> PyResult<Vec<(Element, String, usize)>> {
+        let mode = match mode {
+            "substring" => SearchMode::Substring,
+            "prefix" => SearchMode::Prefix,
+            "regex" => SearchMode::Regex,
+            "fuzzy" => SearchMode::Fuzzy(max_distance),
+            other => return Err(PyValueError::new_err(format!("unknown search mode '{other}' (substring, prefix, regex or fuzzy)"))),
+        };
+        Ok(self.state.search_elements(query, mode, case_insensitive, None, limit)?
+            .into_iter()
+            .map(|r| (r.id, r.name, r.score))
+            .collect())
+    }
+
+    /// neal case version of the element (e.g. `Hello world` -> `Hello World`)
+    fn neal_case(&self, name: &str) -> PyResult<String> {
+        let id = self.id(name)?;
+        self.name(self.state.neal_case_map[id as usize]).map(str::to_string)
+    }
+
+    /// `u32` array, index is the element id, value is the id of its neal case version
+    fn neal_case_map_array<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        u32_bytes(py, &self.state.neal_case_map)
+    }
+
+
+    /// `u32` array of `[first, second, result]` rows, sorted by `(first, second)`
+    fn recipes_array<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        let mut recipes: Vec<[u32; 3]> = self.state.recipes_ing.iter()
+            .map(|(&(f, s), &r)| [f, s, r])
+            .collect();
+        recipes.sort_unstable();
+        u32_bytes(py, recipes.as_flattened())
+    }
+
+    /// the result of `first + second`, or `None` if that recipe isn't known
+    fn recipe(&self, first: &str, second: &str) -> PyResult<Option<String>> {
+        let (f, s) = (self.id(first)?, self.id(second)?);
+        self.state.recipes_ing.get(&(f, s))
+            .or_else(|| self.state.recipes_ing.get(&(s, f)))
+            .map(|&r| self.name(r).map(str::to_string))
+            .transpose()
+    }
+
+    /// all `(first, second)` that result in `result`
+    fn recipes_for(&self, result: &str) -> PyResult<Vec<(String, String)>> {
+        let r = self.id(result)?;
+        let mut recipes: Vec<(String, String)> = self.state.recipes_ing.iter()
+            .filter(|&(_, &res)| res == r)
+            .map(|(&(f, s), _)| (self.state.num_to_str_fn(f), self.state.num_to_str_fn(s)))
+            .collect();
+        recipes.sort_unstable();
+        Ok(recipes)
+    }
+
+    /// all `(other, result)` that use `element` as an ingredient
+    fn uses_of(&self, element: &str) -> PyResult<Vec<(String, String)>> {
+        let e = self.id(element)?;
+        let mut uses: Vec<(String, String)> = self.state.recipes_ing.iter()
+            .filter_map(|(&(f, s), &r)| {
+                let other = if f == e { s } else if s == e { f } else { return None };
+                Some((self.state.num_to_str_fn(other), self.state.num_to_str_fn(r)))
+            })
+            .collect();
+        uses.sort_unstable();
+        Ok(uses)
+    }
+
+
+    /// runs `LayerExplorer::start`, returns `(element_ids, depths)` as two `u32` arrays (sorted by id).
+    /// recipes that would be needed get collected in the to_request set like in rust.
+    #[pyo3(signature = (base_elements, max_steps, multi_thread=true, generate_lineages_file=false))]
+    fn layer_explore<'py>(
+        &self, py: Python<'py>, base_elements: Vec<String>, max_steps: usize, multi_thread: bool, generate_lineages_file: bool
+    ) -> PyResult<(Bound<'py, PyBytes>, Bound<'py, PyBytes>)> {
+        let base_elements = base_elements.iter()
+            .map(|x| self.id(x))
+            .collect::<PyResult<Vec<Element>>>()?;
+
+        let state = &self.state;
+        let mut depths: Vec<(Element, usize)> = py.detach(|| {
+            LayerExplorer::start(state, &base_elements, max_steps, multi_thread, generate_lineages_file)
+                .depth_map()
+                .into_iter()
+                .collect()
+        });
+        depths.sort_unstable();
+
+        let elements: Vec<u32> = depths.iter().map(|&(e, _)| e).collect();
+        let depths: Vec<u32> = depths.iter().map(|&(_, d)| d as u32).collect();
+        Ok((u32_bytes(py, &elements), u32_bytes(py, &depths)))
+    }
+
+    /// best lineage out of all lineage methods, as `(first, second, result)` steps
+    fn lineage(&self, py: Python<'_>, goals: Vec<String>) -> PyResult<Vec<(String, String, String)>> {
+        // generate_lineage_multiple_methods panics on unknown goals
+        for goal in &goals {
+            self.id(&self.state.case_name(goal))?;
+        }
+
+        let state = &self.state;
+        let lineage = py.detach(|| {
+            let goals: Vec<&str> = goals.iter().map(String::as_str).collect();
+            let recipes_result_map = state.get_recipes_result_map();
+            let recipes_uses_map = state.get_recipes_uses_map();
+            let mut heuristic_map = state.get_element_heuristic_map(&recipes_uses_map);
+
+            state.generate_lineage_multiple_methods(&goals, &mut heuristic_map, &recipes_result_map, &recipes_uses_map, false)
+                .get_best()
+        });
+
+        let lineage = lineage.ok_or_else(|| PyValueError::new_err(format!("no lineage found for {goals:?}")))?;
+        Ok(lineage.steps.iter()
+            .map(|&[f, s, r]| (self.state.num_to_str_fn(f), self.state.num_to_str_fn(s), self.state.num_to_str_fn(r)))
+            .collect())
+    }
+
+    /// `u32` array of dead element ids (see `find_dead_elements`)
+    fn dead_elements<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        let state = &self.state;
+        let dead = py.detach(|| state.find_dead_elements());
+        u32_bytes(py, &dead)
+    }
+}
+
+
+
+#[pymodule]
+fn infinite_craft_shenanigance(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyRecipesState>()?;
+    Ok(())
+}
@@ -0,0 +1,125 @@
+use std::{collections::VecDeque, fs::File, io::{self, BufWriter, Write}, time::Instant};
+use num_format::ToFormattedString;
+use colored::Colorize;
+
+use crate::{RECIPE_FILES_FOLDER, structures::{Element, RecipesState, NOTHING_ID, UNKNOWN_ID, sort_recipe_tuple}};
+
+
+
+#[derive(Debug, Clone)]
+pub struct UnreachableElement {
+    pub element: Element,
+    /// every recipe that results in `element` needs at least one of these (unreachable) ingredients.
+    /// empty if no known recipe results in it at all.
+    pub blocking_ingredients: Vec<Element>,
+}
+
+/// everything that can(not) be crafted from a start set, using known recipes only.
+/// all elements are `case_id`s (neal case unless `CaseMode::Exact`), like in the result/uses maps.
+#[derive(Debug, Clone)]
+pub struct ReachabilityReport {
+    pub start_elements: Vec<Element>,
+    /// indexed by element id (only `case_id`s are ever `true`)
+    pub reachable: Vec<bool>,
+    pub unreachable: Vec<UnreachableElement>,
+}
+
+
+
+impl RecipesState {
+    /// the full closure of everything craftable from `start_elements` (e.g. `BASE_IDS`),
+    /// plus every element that isn't in it and what blocks it.
+    pub fn reachability(&self, start_elements: &[Element]) -> ReachabilityReport {
+        let start_time = Instant::now();
+        let recipes_uses_map = self.get_recipes_uses_map();
+        let recipes_result_map = self.get_recipes_result_map();
+
+        let start_elements: Vec<Element> = start_elements.iter().map(|&e| self.case_id(e)).collect();
+        let mut reachable = vec![false; self.num_to_str.len()];
+        let mut queue: VecDeque<Element> = VecDeque::new();
+        for &e in &start_elements {
+            if !reachable[e as usize] {
+                reachable[e as usize] = true;
+                queue.push_back(e);
+            }
+        }
+
+        // a recipe gets looked at when its second ingredient becomes reachable,
+        // at that point the other one is already marked.
+        while let Some(element) = queue.pop_front() {
+            for &(other, result) in &recipes_uses_map[element as usize] {
+                if result == NOTHING_ID || result == UNKNOWN_ID { continue; }
+                if reachable[other as usize] && !reachable[result as usize] {
+                    reachable[result as usize] = true;
+                    queue.push_back(result);
+                }
+            }
+        }
+
+        let unreachable = (0..self.num_to_str.len() as Element)
+            .filter(|&e| self.case_id(e) == e && !reachable[e as usize] && e != NOTHING_ID && e != UNKNOWN_ID)
+            .map(|element| {
+                let mut blocking_ingredients: Vec<Element> = recipes_result_map[element as usize].iter()
+                    .flat_map(|&(f, s)| [f, s])
+                    .filter(|&i| !reachable[i as usize])
+                    .collect();
+                blocking_ingredients.sort_unstable();
+                blocking_ingredients.dedup();
+                UnreachableElement { element, blocking_ingredients }
+            })
+            .collect::<Vec<_>>();
+
+        println!("Reachability from {:?} ({:?}) - reachable: {}, unreachable: {}",
+            self.num_to_strs_fn(&start_elements),
+            start_time.elapsed(),
+            reachable.iter().filter(|&&r| r).count().to_formatted_string(&num_format::Locale::en).green(),
+            unreachable.len().to_formatted_string(&num_format::Locale::en).red(),
+        );
+        ReachabilityReport { start_elements, reachable, unreachable }
+    }
+
+
+    /// adds every `=unknown=` recipe that has both ingredients reachable to `to_request_recipes`.
+    /// those are the only requests that could unblock something.
+    /// save them with `state.extract_to_request().save(...)`, like after the explorers.
+    pub fn add_unblocking_requests(&self, report: &ReachabilityReport) -> usize {
+        let mut added = 0;
+        for (&(f, s), &r) in &self.recipes_ing {
+            let is_reachable = |e: Element| report.reachable[self.case_id(e) as usize];
+            if r == UNKNOWN_ID && is_reachable(f) && is_reachable(s) && self.to_request_recipes.insert(sort_recipe_tuple((f, s))) {
+                added += 1;
+            }
+        }
+        println!("Added {} unblocking requests (to_request: {})", added.to_string().green(), self.to_request_recipes.len());
+        added
+    }
+}
+
+
+
+
+impl ReachabilityReport {
+    pub fn is_reachable(&self, state: &RecipesState, element: Element) -> bool {
+        self.reachable[state.case_id(element) as usize]
+    }
+
+    /// writes every unreachable element as `Element: blocked by A, B` into the `RECIPE_FILES_FOLDER`
+    pub fn write_to_file(&self, state: &RecipesState, file_name: &str) -> io::Result<()> {
+        let file = File::create(format!("{RECIPE_FILES_FOLDER}/{file_name}"))?;
+        let mut writer = BufWriter::new(file);
+
+        writeln!(writer, "start: {:?}, reachable: {}, unreachable: {}\n",
+            state.num_to_strs_fn(&self.start_elements),
+            self.reachable.iter().filter(|&&r| r).count(),
+            self.unreachable.len(),
+        )?;
+        for u in &self.unreachable {
+            if u.blocking_ingredients.is_empty() {
+                writeln!(writer, "{}: never a result", state.num_to_str[u.element as usize])?;
+            } else {
+                writeln!(writer, "{}: blocked by {}", state.num_to_str[u.element as usize], state.num_to_strs_fn(&u.blocking_ingredients).join(", "))?;
+            }
+        }
+        writer.flush()
+    }
+}
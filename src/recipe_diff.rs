@@ -0,0 +1,215 @@
+use serde::{Deserialize, Serialize};
+use std::{fs::File, io::{self, BufReader, BufWriter, Write}, time::Instant};
+use rayon::prelude::*;
+use colored::Colorize;
+
+use crate::{RECIPE_FILES_FOLDER, structures::{Element, RecipesState, sort_recipe_tuple, neal_case_name}};
+
+
+
+/// `first + second = result`
+pub type DiffRecipe = [String; 3];
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct RecipeChange {
+    pub first: String,
+    pub second: String,
+    pub old: String,
+    pub new: String,
+}
+
+
+/// everything that changed from one `RecipesState` to another, matched by element name (not id).
+/// can be saved as a patch file and applied to any other database.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RecipesDiff {
+    /// element names that only exist in the new state
+    pub new_elements: Vec<String>,
+    pub added: Vec<DiffRecipe>,
+    pub removed: Vec<DiffRecipe>,
+    /// results that are the same in neal case (e.g. `Hello world` -> `Hello World`)
+    pub case_only_changes: Vec<RecipeChange>,
+    pub real_changes: Vec<RecipeChange>,
+}
+
+
+#[derive(Debug, Clone, Default)]
+pub struct PatchApplyReport {
+    pub applied: usize,
+    /// the database already looked like the patch wanted it to
+    pub already_applied: usize,
+    /// recipes where the database has a different result than the patch expected, these are skipped
+    pub conflicts: Vec<RecipeChange>,
+}
+
+
+
+
+/// not every code path inserts recipes sorted, so look up both orders
+fn get_recipe(state: &RecipesState, first: Element, second: Element) -> Option<Element> {
+    state.recipes_ing.get(&(first, second))
+        .or_else(|| state.recipes_ing.get(&(second, first)))
+        .copied()
+}
+
+
+impl RecipesState {
+    /// diffs `self` (old) against `new_state` (new).
+    /// e.g. `my_db.diff(&teammates_db)` shows what the teammate would add.
+    /// (if `new_state` is only a partial session file, everything it doesn't have ends up in `removed`)
+    pub fn diff(&self, new_state: &Self) -> RecipesDiff {
+        let start_time = Instant::now();
+
+        let old_str_to_num = self.get_str_to_num_map();
+        let new_str_to_num = new_state.get_str_to_num_map();
+        let new_to_old = |id: Element| old_str_to_num.get(&new_state.num_to_str[id as usize]).copied();
+        let old_to_new = |id: Element| new_str_to_num.get(&self.num_to_str[id as usize]).copied();
+
+        let mut new_elements: Vec<String> = new_state.num_to_str.par_iter()
+            .filter(|name| !old_str_to_num.contains_key(*name))
+            .cloned()
+            .collect();
+
+        // added + changed (from the view of the new state)
+        let (mut added, changes): (Vec<DiffRecipe>, Vec<RecipeChange>) = new_state.recipes_ing.par_iter()
+            .filter_map(|(&(f, s), &r)| {
+                let old_result = new_to_old(f).zip(new_to_old(s))
+                    .and_then(|(old_f, old_s)| get_recipe(self, old_f, old_s));
+                let [first, second, new] = [f, s, r].map(|x| new_state.num_to_str_fn(x));
+
+                match old_result {
+                    None => Some(Err([first, second, new])),
+                    Some(old_r) if self.num_to_str[old_r as usize] == new => None,
+                    Some(old_r) => Some(Ok(RecipeChange { first, second, old: self.num_to_str_fn(old_r), new })),
+                }
+            })
+            .partition_map(|x| match x {
+                Err(added) => rayon::iter::Either::Left(added),
+                Ok(change) => rayon::iter::Either::Right(change),
+            });
+
+        // removed (from the view of the old state)
+        let mut removed: Vec<DiffRecipe> = self.recipes_ing.par_iter()
+            .filter(|&(&(f, s), _)| {
+                old_to_new(f).zip(old_to_new(s))
+                    .is_none_or(|(new_f, new_s)| get_recipe(new_state, new_f, new_s).is_none())
+            })
+            .map(|(&(f, s), &r)| [f, s, r].map(|x| self.num_to_str_fn(x)))
+            .collect();
+
+        let (mut case_only_changes, mut real_changes): (Vec<RecipeChange>, Vec<RecipeChange>) = changes.into_par_iter()
+            .partition(|c| neal_case_name(&c.old) == neal_case_name(&c.new));
+
+        new_elements.par_sort_unstable();
+        added.par_sort_unstable();
+        removed.par_sort_unstable();
+        case_only_changes.par_sort_unstable();
+        real_changes.par_sort_unstable();
+
+        let diff = RecipesDiff { new_elements, added, removed, case_only_changes, real_changes };
+        println!("Diffed ({}) against ({}) in {:?}", self.pretty_element_and_recipe_count(), new_state.pretty_element_and_recipe_count(), start_time.elapsed());
+        diff.print_summary();
+        diff
+    }
+
+
+    /// applies a patch from `diff`. a recipe only gets changed/removed if it still has the result
+    /// the patch expects, everything else is reported as a conflict and left alone.
+    /// only added and changed results create elements (`new_elements` is just for reviewing),
+    /// recipes with an ingredient the database doesn't have count as not there.
+    /// applied recipes lose their `provenance` source (it's not the file they came from anymore).
+    pub fn apply_patch(&mut self, diff: &RecipesDiff) -> PatchApplyReport {
+        let start_time = Instant::now();
+        let mut str_to_num = self.get_str_to_num_map();
+        let mut report = PatchApplyReport::default();
+
+        // removed recipes: old result -> gone
+        // added recipes: nothing -> new result
+        // changes: old result -> new result
+        let removed = diff.removed.iter().map(|[f, s, r]| (f, s, Some(r), None));
+        let added = diff.added.iter().map(|[f, s, r]| (f, s, None, Some(r)));
+        let changes = diff.case_only_changes.iter().chain(&diff.real_changes)
+            .map(|c| (&c.first, &c.second, Some(&c.old), Some(&c.new)));
+
+        for (first, second, expected, wanted) in removed.chain(added).chain(changes) {
+            let ids = str_to_num.get(first).copied().zip(str_to_num.get(second).copied());
+            let current = ids.and_then(|(f, s)| get_recipe(self, f, s)).map(|r| self.num_to_str[r as usize].as_str());
+
+            if current == wanted.map(String::as_str) {
+                report.already_applied += 1;
+            }
+            else if current == expected.map(String::as_str) {
+                // (missing ingredients only get here for added recipes)
+                let (f, s) = ids.unwrap_or_else(|| (
+                    self.variables_add_element_str(first, &mut str_to_num),
+                    self.variables_add_element_str(second, &mut str_to_num),
+                ));
+                for recipe in [(f, s), (s, f)] {
+                    self.recipes_ing.remove(&recipe);
+                    if let Some(recipe_sources) = &mut self.provenance.recipe_sources {
+                        recipe_sources.remove(&recipe);
+                    }
+                }
+                if let Some(wanted) = wanted {
+                    let r = self.variables_add_element_str(wanted, &mut str_to_num);
+                    self.recipes_ing.insert(sort_recipe_tuple((f, s)), r);
+                    self.to_request_recipes.remove(&(f, s));
+                    self.to_request_recipes.remove(&(s, f));
+                }
+                report.applied += 1;
+            }
+            else {
+                report.conflicts.push(RecipeChange {
+                    first: first.clone(),
+                    second: second.clone(),
+                    old: current.unwrap_or_default().to_string(),
+                    new: wanted.cloned().unwrap_or_default(),
+                });
+            }
+        }
+
+        self.recipes_updated_total += report.applied;
+        println!("Applied patch in {:?} - applied: {}, already applied: {}, conflicts: {} - ({})",
+            start_time.elapsed(),
+            report.applied.to_string().green(),
+            report.already_applied,
+            report.conflicts.len().to_string().red(),
+            self.pretty_element_and_recipe_count(),
+        );
+        report
+    }
+}
+
+
+
+
+impl RecipesDiff {
+    pub fn is_empty(&self) -> bool {
+        self.new_elements.is_empty() && self.added.is_empty() && self.removed.is_empty()
+            && self.case_only_changes.is_empty() && self.real_changes.is_empty()
+    }
+
+    pub fn print_summary(&self) {
+        println!("  - new elements: {}, added: {}, removed: {}, case only changes: {}, real changes: {}",
+            self.new_elements.len().to_string().purple(),
+            self.added.len().to_string().green(),
+            self.removed.len().to_string().red(),
+            self.case_only_changes.len().to_string().yellow(),
+            self.real_changes.len().to_string().red(),
+        );
+    }
+
+    /// saves the diff as a (pretty json) patch file into the `RECIPE_FILES_FOLDER`
+    pub fn save_patch(&self, file_name: &str) -> io::Result<()> {
+        let file = File::create(format!("{RECIPE_FILES_FOLDER}/{file_name}"))?;
+        let mut writer = BufWriter::new(file);
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writer.flush()
+    }
+
+    pub fn load_patch(file_name: &str) -> io::Result<Self> {
+        let file = File::open(format!("{RECIPE_FILES_FOLDER}/{file_name}"))?;
+        Ok(serde_json::from_reader(BufReader::new(file))?)
+    }
+}
+
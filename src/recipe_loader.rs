@@ -1,13 +1,13 @@
 #![deny(clippy::unwrap_used)]
 
 use serde::{Deserialize, Serialize};
-use std::{fs::File, io::{self, BufRead, BufReader, BufWriter, Read, Write}, time::{Instant, SystemTime, UNIX_EPOCH}};
+use std::{fs::{self, File}, io::{self, BufRead, BufReader, BufWriter, Read, Write}, time::{Instant, SystemTime, UNIX_EPOCH}};
 use rustc_hash::{FxBuildHasher, FxHashMap};
 use rayon::prelude::*;
 
 use libdeflater::{CompressionLvl, Compressor, Decompressor};
 
-use crate::{RECIPE_FILES_FOLDER, structures::{RecipesState, sort_recipe_tuple, UNKNOWN_ID, NOTHING_ID, start_case_unicode, Element}};
+use crate::{RECIPE_FILES_FOLDER, cancel, lineages_file::{LineagesFilter, filter_lineages_file, lineages_file_path}, progress::Progress, provenance::{RecipeSource, SourceId}, recipe_merge::MergeAction, structures::{RecipesState, sort_recipe_tuple, UNKNOWN_ID, NOTHING_ID, neal_case_name}};
 
 
 
@@ -27,6 +27,12 @@ struct RecipesNum {
 
     #[serde(default)]
     recipes: FxHashMap<u32, FxHashMap<u32, u32>>,
+
+    /// provenance (only if it was tracked), same layout as `recipes` but with a `SourceId` instead of the result
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    sources: Vec<RecipeSource>,
+    #[serde(default, skip_serializing_if = "FxHashMap::is_empty")]
+    recipe_sources: FxHashMap<u32, FxHashMap<u32, SourceId>>,
 }
 
 #[derive(Deserialize, Serialize)]
@@ -43,6 +49,8 @@ struct RecipesGzipItemData {
     id: u32,
     text: String,
     #[serde(default)]
+    emoji: Option<String>,
+    #[serde(default)]
     recipes: Vec<(u32, u32)>,
 }
 
@@ -62,21 +70,20 @@ struct RecipesGzipItemDataRef<'a> {
     recipes: &'a [(u32, u32)],
 }
 
-#[derive(Deserialize, Debug)]
-struct CoolJsonLineagesFile {
-    elements: FxHashMap<String, Vec<Vec<Vec<String>>>>,
-}
-
 
 impl RecipesState {
     /// loads a recipe file in of the 3 formats.
     /// the base-folder is the ``RECIPE_FILES_FOLDER`` (at the top of main.rs)
     pub fn load(&mut self, file_name: &str, format: RecipesFile) -> io::Result<()> {
+        if cancel::is_cancelled() {
+            return Err(io::Error::new(io::ErrorKind::Interrupted, format!("canceled, not loading {file_name}")));
+        }
         println!("Loading {} - {:?} - Before ({})", file_name, format, self.pretty_element_and_recipe_count());
         let start_time = Instant::now();
 
         let file_path = format!("{RECIPE_FILES_FOLDER}/{file_name}");
         let file = &mut File::open(file_path)?;
+        self.provenance.begin_file_source(file_name);
 
         let response = match format {
             RecipesFile::ICSaveFile => self.load_recipes_gzip(file),
@@ -85,35 +92,58 @@ impl RecipesState {
         };
 
         match response {
-            Err(e) => panic!("  - FAILED TO LOAD... ({:?}): {}", start_time.elapsed(), e),
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => println!("  - Canceled! ({:?}): {}", start_time.elapsed(), e),
+            Err(ref e) => println!("  - FAILED TO LOAD... ({:?}): {}", start_time.elapsed(), e),
             Ok(()) => println!("  - Complete! - {:?} - After ({})", start_time.elapsed(), self.pretty_element_and_recipe_count()),
         }
+        self.provenance.loading_source = None;
+        if response.is_ok() {
+            self.check_names_after_load();
+        }
+        self.export_load_save_metrics(&format!("Load {file_name}"), start_time);
         response
     }
 
     
     /// saves a recipe file in of the 3 formats.
     /// the base-folder is the `RECIPE_FILES_FOLDER` (at the top of main.rs)
+    /// 
+    /// it first writes everything into a `.tmp` file and only then replaces the real file,
+    /// so aborting in the middle of a save never leaves a half written file behind.
     pub fn save(&self, file_name: &str, format: RecipesFile) -> io::Result<()> {
         println!("Saving {} - {:?} - ({})", file_name, format, self.pretty_element_and_recipe_count());
         let start_time = Instant::now();
 
-        let file_path = &format!("{RECIPE_FILES_FOLDER}/{file_name}");
+        let file_path = format!("{RECIPE_FILES_FOLDER}/{file_name}");
+        let temp_file_path = &format!("{file_path}.tmp");
 
         let response = match format {
-            RecipesFile::ICSaveFile => self.save_recipes_gzip(file_path),
-            RecipesFile::JSONRecipesNum => self.save_recipes_num(file_path),
-            RecipesFile::JSONOldDepthExplorerRecipes => self.save_recipes_old_depth_explorer(file_path),
-        };
+            RecipesFile::ICSaveFile => self.save_recipes_gzip(temp_file_path),
+            RecipesFile::JSONRecipesNum => self.save_recipes_num(temp_file_path),
+            RecipesFile::JSONOldDepthExplorerRecipes => self.save_recipes_old_depth_explorer(temp_file_path),
+        }.and_then(|()| fs::rename(temp_file_path, &file_path));
 
         match response {
             Err(ref e) => println!("  - FAILED TO SAVE... ({:?}): {}", start_time.elapsed(), e),
-            Ok(()) => println!("  - Complete! ({:?})", start_time.elapsed()),
+            Ok(()) => {
+                println!("  - Complete! ({:?})", start_time.elapsed());
+                self.export_load_save_metrics(&format!("Save {file_name}"), start_time);
+            },
         }
         response
     }
 
 
+    fn export_load_save_metrics(&self, job: &str, start_time: Instant) {
+        let progress = Progress::new(job, 1);
+        progress.inc(1);
+        progress.set_counter("elements", self.num_to_str.len() as u64);
+        progress.set_counter("recipes", self.recipes_ing.len() as u64);
+        progress.set_counter("duration_ms", start_time.elapsed().as_millis() as u64);
+        progress.export();
+    }
+
+
 
 
 
@@ -143,9 +173,24 @@ impl RecipesState {
                 recipes_ing.insert(sort_recipe_tuple((*first_ingredient, *second_ingredient)), *result);
             }
         }
+
+        // the file's own source ids -> ours
+        let mut recipe_sources = FxHashMap::default();
+        if self.provenance.is_tracking() {
+            let source_ids: Vec<SourceId> = data.sources.into_iter()
+                .map(|source| self.provenance.add_source(source))
+                .collect();
+            for (first_ingredient, inner_map) in &data.recipe_sources {
+                for (second_ingredient, source) in inner_map {
+                    if let Some(&source) = source_ids.get(*source as usize) {
+                        recipe_sources.insert(sort_recipe_tuple((*first_ingredient, *second_ingredient)), source);
+                    }
+                }
+            }
+        }
         println!("  - Recipe processing complete: {:?}", recipe_process_time.elapsed());
 
-        self.merge_new_variables_with_new(&mut data.num_to_str, &mut str_to_num, recipes_ing)
+        self.merge_new_variables_with_new(&mut data.num_to_str, &mut str_to_num, recipes_ing, &recipe_sources)
     }
 
 
@@ -161,14 +206,25 @@ impl RecipesState {
             recipes.entry(recipe.0).or_default().insert(recipe.1, result);
         }
 
-        let data = RecipesNum { recipes, num_to_str: self.num_to_str.clone() };
+        // provenance, with the same keys as `recipes`
+        let mut recipe_sources: FxHashMap<u32, FxHashMap<u32, SourceId>> = FxHashMap::default();
+        if self.provenance.is_tracking() {
+            for &recipe in self.recipes_ing.keys() {
+                if let Some(source) = self.provenance.recipe_source(recipe) {
+                    recipe_sources.entry(recipe.0).or_default().insert(recipe.1, source);
+                }
+            }
+        }
+        let sources = if recipe_sources.is_empty() { Vec::new() } else { self.provenance.sources.clone() };
+
+        let data = RecipesNum { recipes, num_to_str: self.num_to_str.clone(), sources, recipe_sources };
         println!("  - Recipe Processing complete: {:?}", recipe_process_time.elapsed());
 
 
         let file = File::create(file_path)?;
         let mut writer = BufWriter::with_capacity(1024 * 1024, file);
         serde_json::to_writer(&mut writer, &data)?;
-        Ok(())
+        writer.flush()
     }
 
 
@@ -216,7 +272,7 @@ impl RecipesState {
             recipes_ing.insert(comb, get_id(result));
         }
 
-        self.merge_new_variables_with_new(&mut num_to_str, &mut str_to_num, recipes_ing)
+        self.merge_new_variables_with_new(&mut num_to_str, &mut str_to_num, recipes_ing, &FxHashMap::default())
     }
 
 
@@ -245,7 +301,7 @@ impl RecipesState {
         let file = File::create(file_path)?;
         let mut writer = BufWriter::with_capacity(1024 * 1024, file);
         serde_json::to_writer_pretty(&mut writer, &recipes)?;
-        Ok(())
+        writer.flush()
     }
 
 
@@ -265,29 +321,7 @@ impl RecipesState {
 
 
     fn load_recipes_gzip(&mut self, file: &mut File) -> io::Result<()> {
-        let deserialize_time = Instant::now();
-
-        // 1. Read compressed data
-        let mut gz_buffer = Vec::new();
-        file.read_to_end(&mut gz_buffer)?;
-
-        // 2. Get expected size from GZIP footer
-        if gz_buffer.len() < 4 { return Err(io::Error::new(io::ErrorKind::InvalidData, "Gzip data too short")); }
-        let isize = u32::from_le_bytes(
-            gz_buffer[gz_buffer.len()-4..].try_into().map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "gz_buffer issue..."))?
-        ) as usize;
-
-        // 3. Decompress
-        let mut decompressor = Decompressor::new();
-        let mut out_buf = vec![0u8; isize];
-        let actual_size = decompressor.gzip_decompress(&gz_buffer, &mut out_buf)
-            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("Decompression failed: {e:?}")))?;
-        out_buf.truncate(actual_size); // Adjust size if ISIZE was wrong
-
-        // 4. Parse JSON
-        let data: RecipesGzip = serde_json::from_slice(&out_buf)?;
-        println!("  - Deserialization complete: {:?}", deserialize_time.elapsed());
-
+        let data = read_recipes_gzip(file)?;
 
         let mut num_to_str: Vec<String> = vec![String::new(); data.items.len()];
         let mut str_to_num: FxHashMap<String, u32> = FxHashMap::default();
@@ -305,7 +339,7 @@ impl RecipesState {
             }
         }
 
-        self.merge_new_variables_with_new(&mut num_to_str, &mut str_to_num, recipes_ing)
+        self.merge_new_variables_with_new(&mut num_to_str, &mut str_to_num, recipes_ing, &FxHashMap::default())
     }
 
 
@@ -370,8 +404,7 @@ impl RecipesState {
 
         compressed_buffer.resize(actual_compressed_size, 0);
         writer.write_all(&compressed_buffer)?;
-
-        Ok(())
+        writer.flush()
     }
 
 
@@ -466,16 +499,21 @@ impl RecipesState {
         &mut self,
         new_num_to_str: &mut Vec<String>,
         new_str_to_num: &mut FxHashMap<String, u32>,
-        new_recipes_ing: FxHashMap<(u32, u32), u32>
+        new_recipes_ing: FxHashMap<(u32, u32), u32>,
+        // per-recipe sources the file itself had (already mapped to our `SourceId`s), everything else comes from the file
+        new_recipe_sources: &FxHashMap<(u32, u32), SourceId>,
     ) -> io::Result<()> {
         // println!("  - Merging new Elements: {}, Recipes: {}", new_num_to_str.len(), new_recipes_ing.len());
+        if cancel::is_cancelled() {
+            return Err(io::Error::new(io::ErrorKind::Interrupted, "canceled before merging, nothing got changed"));
+        }
 
         // let neal_case_time = Instant::now();
         let mut new_neal_case_map: Vec<u32> = Vec::with_capacity(new_num_to_str.len());
         let mut added: Vec<String> = Vec::new();
 
         for str in new_num_to_str.iter() {
-            let neal_str = start_case_unicode(str);
+            let neal_str = neal_case_name(str);
             if let Some(x) = new_str_to_num.get(&neal_str) {
                 // neal case version exists, link to it
                 new_neal_case_map.push(*x);
@@ -535,24 +573,33 @@ impl RecipesState {
         // merge recipes_ing
         let recipes_ing_merge_time = Instant::now();
 
-        let transformed_recipes: Vec<((Element, Element), Element)> = new_recipes_ing
+        // the file that is currently getting loaded
+        let file_source = self.provenance.loading_source;
+
+        let transformed_recipes: Vec<_> = new_recipes_ing
             .into_par_iter()
             .filter_map(|((first, second), result)| {
+                let source = new_recipe_sources.get(&(first, second)).copied().or(file_source);
                 let existing_first = newnum_to_existingnum[first as usize].expect("Missing existing ID for first ingredient");
                 let existing_second = newnum_to_existingnum[second as usize].expect("Missing existing ID for second ingredient");
                 let existing_result = newnum_to_existingnum[result as usize].expect("Missing existing ID for result");
 
                 let recipe = sort_recipe_tuple((existing_first, existing_second));
-                // if new recipe is not NOTHING it always gets added
+                // if new recipe is not NOTHING it gets added (real conflicts are decided by the `MergePolicy`)
                 // if new recipe is NOTHING it only gets added if the recipe didn't exist at all
-                if (existing_result != NOTHING_ID && existing_result != UNKNOWN_ID) || !self.recipes_ing.contains_key(&recipe) {
-                    Some((recipe, existing_result))
+                match self.merging.merge_action(&self.provenance, recipe, self.recipes_ing.get(&recipe).copied(), existing_result, source) {
+                    MergeAction::Skip => None,
+                    action => Some((recipe, existing_result, source, action)),
                 }
-                else { None }
             })
             .collect();
 
-        self.recipes_ing.extend(transformed_recipes);
+        for (recipe, result, source, action) in transformed_recipes {
+            self.apply_merge_action(recipe, result, source, action);
+        }
+        if self.merging.conflict_count != 0 {
+            println!("  - Merge conflicts so far: {} ({:?})", self.merging.conflict_count, self.merging.policy);
+        }
 
         println!("  - Merging recipes_ing complete: {:?}", recipes_ing_merge_time.elapsed());
 
@@ -610,76 +657,108 @@ impl RecipesState {
 
 
     /// `is_json_format == false`
-    /// tries to just add all lines that have a ` + ` and a ` = ` afterwards
-    /// e.g. `  Water + Fire = Steam`
+    /// adds every `A + B = C` line of a text lineages file (see `load_recipes_from_text_lineages`)
     /// 
     /// `is_json_format == true`
-    /// tries to just add all lines that have a `[String; 3]`
-    /// e.g. ` ["Water", "Fire", "Steam"]`
+    /// parses the json lineages file (see `lineages_file`) and adds every recipe of every lineage
     pub fn load_recipes_from_lineages_file(&mut self, file_name: &str, is_json_format: bool) -> io::Result<()> {
         println!("Loading recipes from lineages file: {file_name}");
         let start_time = Instant::now();
-        let mut str_to_num: FxHashMap<String, u32> = self.get_str_to_num_map();
 
-        let file_path = format!("{RECIPE_FILES_FOLDER}/../Lineages Files/{file_name}");
-        let file = File::open(&file_path)?;
-        let reader = BufReader::new(file);
-        
-        // Process each line in the file.
-        for line in reader.lines() {
-            if !is_json_format {
-                // Split the line into "ing1 + ing2" and "result" parts
-                if let Some((ings, r)) = line?.split_once(" = ")
-                && let Some((f, s)) = ings.split_once(" + ") {
-                    self.variables_add_recipe(f, s, r, &mut str_to_num);
-                }
-            } else {
-                if let Ok([f, s, r]) = serde_json::from_str::<[Box<str>; 3]>(&line?) {
-                    self.variables_add_recipe(&f, &s, &r, &mut str_to_num);
-                }
-            }
+        if is_json_format {
+            self.load_recipes_from_json_lineages_file(file_name)?;
+        } else {
+            let reader = BufReader::new(File::open(lineages_file_path(file_name))?);
+            self.load_recipes_from_text_lineages(reader)?;
         }
 
         println!("  - Lineage file parsing complete: {:?} - {}", start_time.elapsed(), self.pretty_element_and_recipe_count());
         Ok(())
     }
-}
 
 
-pub fn strip_alts_from_json_lineages_file(input_file: &str, output_file: &str) -> io::Result<()> {
-    println!("stripping alts from json lineages file: {input_file}");
-    let start_time = Instant::now();
-    
-    let input_path = format!("{RECIPE_FILES_FOLDER}/../Lineages Files/{input_file}");
-    let output_path = format!("{RECIPE_FILES_FOLDER}/../Lineages Files/{output_file}");
-    let file = File::open(input_path)?;
-    let reader = BufReader::new(file);
-    
-    let out = File::create(output_path)?;
-    let mut writer = BufWriter::new(out);
-
-    let mut skipping_extra_lineages = false;
+    /// tries to just add all lines that have a ` + ` and a ` = ` afterwards,
+    /// e.g. `Water + Fire = Steam  // goal`. lines starting with `//` and `  // ` comments get ignored.
+    ///
+    /// names can have ` + ` or ` = ` in them (`1 + 1 = 2` is an element), so it takes the first split
+    /// where both ingredients already exist (in a lineage they're always base elements or earlier results)
+    pub fn load_recipes_from_text_lineages(&mut self, reader: impl BufRead) -> io::Result<()> {
+        let mut str_to_num: FxHashMap<String, u32> = self.get_str_to_num_map();
 
-    for line in reader.lines() {
-        let line = line?;
-        let trimmed = line.trim();
+        for line in reader.lines() {
+            let line = line?;
+            if line.starts_with("//") { continue; }
+            // drop `  // comments` (goal markers, emojis, ...)
+            let line = line.split_once("  // ").map_or(line.as_str(), |(recipe, _)| recipe);
 
-        // its looking for the end of the element entry: "]]," or "]]"
-        if trimmed == "]]," || trimmed == "]]" {
-            // found the end of a lineage alts block, stop skipping
-            skipping_extra_lineages = false;
-        }
-        // find the end of the first lineage and start of an alt
-        if trimmed == "],[" {
-            skipping_extra_lineages = true;
+            if let Some((f, s, r)) = split_recipe_line(line, &str_to_num) {
+                self.variables_add_recipe(f, s, r, &mut str_to_num);
+            }
         }
-        
-        if !skipping_extra_lineages {
-            // If we aren't skipping, just pipe the line through to the new file
-            writeln!(writer, "{}", line)?;
+        Ok(())
+    }
+}
+
+
+/// `first + second = result`, see `load_recipes_from_text_lineages`
+fn split_recipe_line<'a>(line: &'a str, str_to_num: &FxHashMap<String, u32>) -> Option<(&'a str, &'a str, &'a str)> {
+    let mut first_split = None;
+    for (equals, _) in line.match_indices(" = ") {
+        let (ings, result) = (&line[..equals], &line[equals + 3..]);
+        for (plus, _) in ings.match_indices(" + ") {
+            let (f, s) = (&ings[..plus], &ings[plus + 3..]);
+            if str_to_num.contains_key(f) && str_to_num.contains_key(s) {
+                return Some((f, s, result));
+            }
+            first_split.get_or_insert((f, s, result));
         }
     }
+    first_split
+}
+
+
+/// decompresses and parses an infinite craft save file (`.ic`)
+fn read_recipes_gzip(file: &mut File) -> io::Result<RecipesGzip> {
+    let deserialize_time = Instant::now();
+
+    // 1. Read compressed data
+    let mut gz_buffer = Vec::new();
+    file.read_to_end(&mut gz_buffer)?;
+
+    // 2. Get expected size from GZIP footer
+    if gz_buffer.len() < 4 { return Err(io::Error::new(io::ErrorKind::InvalidData, "Gzip data too short")); }
+    let isize = u32::from_le_bytes(
+        gz_buffer[gz_buffer.len()-4..].try_into().map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "gz_buffer issue..."))?
+    ) as usize;
+
+    // 3. Decompress
+    let mut decompressor = Decompressor::new();
+    let mut out_buf = vec![0u8; isize];
+    let actual_size = decompressor.gzip_decompress(&gz_buffer, &mut out_buf)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("Decompression failed: {e:?}")))?;
+    out_buf.truncate(actual_size); // Adjust size if ISIZE was wrong
 
-    println!("Finished reducing lineages! Saved to: {output_file} - {:?}", start_time.elapsed());
-    writer.flush()
-}
\ No newline at end of file
+    // 4. Parse JSON
+    let data: RecipesGzip = serde_json::from_slice(&out_buf)?;
+    println!("  - Deserialization complete: {:?}", deserialize_time.elapsed());
+    Ok(data)
+}
+
+
+/// element name -> emoji, from an infinite craft save file (`.ic`) in the `RECIPE_FILES_FOLDER`.
+/// the `RecipesState` doesn't keep emojis, this is only for pretty output (e.g. text lineages files)
+pub fn load_emojis_from_ic_save_file(file_name: &str) -> io::Result<FxHashMap<String, String>> {
+    let mut file = File::open(format!("{RECIPE_FILES_FOLDER}/{file_name}"))?;
+    Ok(read_recipes_gzip(&mut file)?.items.into_iter()
+        .filter_map(|item| Some((item.text, item.emoji?)))
+        .collect())
+}
+
+
+/// keeps only the first lineage of every element (see `filter_lineages_file`)
+pub fn strip_alts_from_json_lineages_file(input_file: &str, output_file: &str) -> io::Result<()> {
+    println!("stripping alts from json lineages file: {input_file}");
+    filter_lineages_file(input_file, output_file, &LineagesFilter::strip_alts())?;
+    println!("Finished reducing lineages! Saved to: {output_file}");
+    Ok(())
+}
@@ -0,0 +1,155 @@
+use std::{fs::File, io::{self, BufWriter, Write}};
+use colored::Colorize;
+
+use crate::{RECIPE_FILES_FOLDER, provenance::{Provenance, SourceId}, structures::{Element, RecipesState, NOTHING_ID, UNKNOWN_ID}};
+
+
+
+/// what happens when a loaded file has a different (real) result for a recipe than the one that's already there.
+///
+/// `Nothing` and `=unknown=` are never a conflict: they only fill gaps, and a real result always replaces them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MergePolicy {
+    /// the recipe that got loaded first stays
+    PreferExisting,
+    /// the recipe that got loaded last wins (this is how it always worked)
+    #[default]
+    PreferIncoming,
+    /// the recipe from the source with the newest timestamp wins
+    /// (see `RecipeSource::timestamp`, ties go to the incoming file)
+    PreferNewest,
+    /// the existing recipe stays, the incoming one only ends up in the conflict report
+    KeepBothAndFlag,
+}
+
+
+#[derive(Debug, Clone)]
+pub struct MergeConflict {
+    pub first: String,
+    pub second: String,
+    pub existing: String,
+    pub existing_source: String,
+    pub incoming: String,
+    pub incoming_source: String,
+    /// true if the incoming result replaced the existing one
+    pub took_incoming: bool,
+}
+
+
+/// everything about merging multiple loaded files
+#[derive(Debug, Clone, Default)]
+pub struct MergeState {
+    pub policy: MergePolicy,
+    /// keep every conflict in `conflicts` (always on for `KeepBothAndFlag`), see `record_merge_conflicts`
+    pub record_conflicts: bool,
+    /// every disagreement between two loaded files, in load order (only if they get recorded)
+    pub conflicts: Vec<MergeConflict>,
+    /// how many disagreements there were, recorded or not
+    pub conflict_count: usize,
+}
+
+
+/// what `merge_new_variables_with_new` should do with one incoming recipe
+pub(crate) enum MergeAction {
+    Insert,
+    Skip,
+    Conflict { existing: Element, take_incoming: bool },
+}
+
+
+impl MergeState {
+    pub fn is_recording(&self) -> bool {
+        self.record_conflicts || self.policy == MergePolicy::KeepBothAndFlag
+    }
+
+    /// decides what happens to `incoming` if the recipe already results in `existing`
+    pub(crate) fn merge_action(
+        &self, provenance: &Provenance, recipe: (Element, Element), existing: Option<Element>, incoming: Element, incoming_source: Option<SourceId>
+    ) -> MergeAction {
+        let is_real = |r: Element| r != NOTHING_ID && r != UNKNOWN_ID;
+
+        match existing {
+            None => MergeAction::Insert,
+            Some(existing) if existing == incoming => MergeAction::Skip,
+            Some(_) if !is_real(incoming) => MergeAction::Skip,
+            Some(existing) if !is_real(existing) => MergeAction::Insert,
+            Some(existing) => {
+                let take_incoming = match self.policy {
+                    MergePolicy::PreferExisting | MergePolicy::KeepBothAndFlag => false,
+                    MergePolicy::PreferIncoming => true,
+                    MergePolicy::PreferNewest => {
+                        let timestamp = |s: Option<SourceId>| provenance.source(s).map_or(0, |s| s.timestamp);
+                        timestamp(incoming_source) >= timestamp(provenance.recipe_source(recipe))
+                    },
+                };
+                MergeAction::Conflict { existing, take_incoming }
+            },
+        }
+    }
+}
+
+
+
+
+impl RecipesState {
+    /// inserts a recipe that `merge_action` said to insert. conflicts get counted
+    /// (and end up in `merging.conflicts` if they're recorded)
+    pub(crate) fn apply_merge_action(&mut self, recipe: (Element, Element), result: Element, source: Option<SourceId>, action: MergeAction) {
+        match action {
+            MergeAction::Skip => return,
+            MergeAction::Insert => {},
+            MergeAction::Conflict { existing, take_incoming } => {
+                self.merging.conflict_count += 1;
+                if self.merging.is_recording() {
+                    let existing_source = self.provenance.recipe_source(recipe);
+                    self.merging.conflicts.push(MergeConflict {
+                        first: self.num_to_str_fn(recipe.0),
+                        second: self.num_to_str_fn(recipe.1),
+                        existing: self.num_to_str_fn(existing),
+                        existing_source: self.provenance.source_name(existing_source).to_string(),
+                        incoming: self.num_to_str_fn(result),
+                        incoming_source: self.provenance.source_name(source).to_string(),
+                        took_incoming: take_incoming,
+                    });
+                }
+                if !take_incoming { return; }
+            },
+        }
+        self.recipes_ing.insert(recipe, result);
+        if let Some(source) = source {
+            self.provenance.set_recipe_source(recipe, source);
+        }
+    }
+
+    /// changes how conflicting recipes get merged on load, and starts tracking which file every recipe came from
+    /// (`track_provenance`). call this before loading anything, recipes that are already there have no known source.
+    pub fn set_merge_policy(&mut self, policy: MergePolicy) {
+        self.merging.policy = policy;
+        self.track_provenance();
+    }
+
+
+    /// keeps every merge conflict from now on (for `write_merge_conflicts`),
+    /// otherwise only `KeepBothAndFlag` keeps them and the rest just counts them
+    pub fn record_merge_conflicts(&mut self) {
+        self.merging.record_conflicts = true;
+    }
+
+
+    /// writes every recorded merge conflict as `first + second: existing (source) | incoming (source) -> kept`
+    /// into the `RECIPE_FILES_FOLDER`
+    pub fn write_merge_conflicts(&self, file_name: &str) -> io::Result<()> {
+        let file = File::create(format!("{RECIPE_FILES_FOLDER}/{file_name}"))?;
+        let mut writer = BufWriter::new(file);
+
+        writeln!(writer, "policy: {:?}, conflicts: {} (recorded: {})\n", self.merging.policy, self.merging.conflict_count, self.merging.conflicts.len())?;
+        for c in &self.merging.conflicts {
+            let kept = if c.took_incoming { &c.incoming } else { &c.existing };
+            writeln!(writer, "{} + {}: {} ({}) | {} ({}) -> {}", c.first, c.second, c.existing, c.existing_source, c.incoming, c.incoming_source, kept)?;
+        }
+        writer.flush()?;
+
+        println!("Wrote {} merge conflicts to {}", self.merging.conflicts.len().to_string().red(), file_name);
+        Ok(())
+    }
+}
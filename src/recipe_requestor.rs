@@ -1,30 +1,169 @@
 use num_format::ToFormattedString;
 use serde::Deserialize;
 
-use std::{sync::{Arc, Mutex, OnceLock}, time::Instant};
+use std::{cmp::Reverse, collections::BinaryHeap, io, sync::{Arc, Mutex, OnceLock, RwLock, atomic::{AtomicBool, AtomicUsize, AtomicU64, Ordering}}, time::Instant};
 use futures::stream::StreamExt;
+use rustc_hash::FxHashMap;
 use tokio::{task, time::Duration};
 use colored::Colorize;
 
-use crate::structures::RecipesState;
+use crate::{cancel, provenance};
+use crate::progress::{Progress, ProgressSnapshot};
+use crate::structures::{Element, ElementHeuristicMap, RecipesState};
 
 
 
 
 
-const REQUEST_SERVER_URL: &str = "http://localhost:3000";
+/// used if `set_request_endpoints` never gets called
+const DEFAULT_REQUEST_SERVER_URL: &str = "http://localhost:3000";
 const COMBINE_RETRIES: u64 = 5;
 
+/// after this many failed requests in a row, an endpoint gets ejected and won't get any requests
+const ENDPOINT_EJECT_AFTER_FAILURES: usize = 10;
+/// an ejected endpoint gets a single probe request again after this time.
+/// if the probe works it is back in, otherwise it stays ejected for another interval.
+const ENDPOINT_REPROBE_INTERVAL: Duration = Duration::from_secs(30);
+
 /// the timeout from rust to the local:3000 server
 const COMBINE_TIMEOUT: Duration = Duration::from_mins(5);
 
 /// amount of outgoing requests at each time from rust to the local:3000 server
 /// set to 150 by default to make sure that its a constant stream of requests. (you can modify it to something larger)
-const MAX_CONCURRENT_REQUESTS: usize = 150;
+pub(crate) const MAX_CONCURRENT_REQUESTS: usize = 150;
 const COMBINE_INTERVAL_MESSAGE_SECS: Duration = Duration::from_mins(1);
 
 
 static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+/// `None` until the first request or `set_request_endpoints`
+static ENDPOINTS: RwLock<Option<Arc<[EndpointState]>>> = RwLock::new(None);
+
+
+/// a combination-proxy server. (see README.md)
+/// `weight` is how many requests it gets compared to the other endpoints,
+/// e.g. weights `[2, 1]` send 2/3 of all requests to the first one.
+#[derive(Debug, Clone)]
+pub struct RequestEndpoint {
+    pub url: String,
+    pub weight: u32,
+}
+
+impl RequestEndpoint {
+    pub fn new(url: &str, weight: u32) -> Self {
+        Self { url: url.trim_end_matches('/').to_string(), weight }
+    }
+}
+
+
+#[derive(Debug)]
+struct EndpointState {
+    endpoint: RequestEndpoint,
+    requests: AtomicUsize,
+    successes: AtomicUsize,
+    failures: AtomicUsize,
+    ejections: AtomicUsize,
+    consecutive_failures: AtomicUsize,
+    total_response_ms: AtomicU64,
+    ejected: AtomicBool,
+    /// when it got ejected, or when the last re-probe got sent
+    ejected_since: Mutex<Instant>,
+}
+
+impl EndpointState {
+    fn new(endpoint: RequestEndpoint) -> Self {
+        Self {
+            endpoint,
+            requests: AtomicUsize::new(0),
+            successes: AtomicUsize::new(0),
+            failures: AtomicUsize::new(0),
+            ejections: AtomicUsize::new(0),
+            consecutive_failures: AtomicUsize::new(0),
+            total_response_ms: AtomicU64::new(0),
+            ejected: AtomicBool::new(false),
+            ejected_since: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// ejected endpoints are only available once per `ENDPOINT_REPROBE_INTERVAL` (for the re-probe)
+    fn try_available(&self) -> bool {
+        if !self.ejected.load(Ordering::Relaxed) { return true; }
+
+        let mut ejected_since = self.ejected_since.lock().expect("ejected_since lock poisoned");
+        if ejected_since.elapsed() >= ENDPOINT_REPROBE_INTERVAL {
+            *ejected_since = Instant::now();
+            true
+        } else { false }
+    }
+
+    fn record_success(&self, response_time: Duration) {
+        self.successes.fetch_add(1, Ordering::Relaxed);
+        self.total_response_ms.fetch_add(response_time.as_millis() as u64, Ordering::Relaxed);
+        self.consecutive_failures.store(0, Ordering::Relaxed);
+        if self.ejected.swap(false, Ordering::Relaxed) {
+            println!("{} is back! (re-probe worked)", self.endpoint.url.green());
+        }
+    }
+
+    fn record_failure(&self, reason: &str) {
+        self.failures.fetch_add(1, Ordering::Relaxed);
+        let failures_in_a_row = self.consecutive_failures.fetch_add(1, Ordering::Relaxed) + 1;
+
+        if failures_in_a_row >= ENDPOINT_EJECT_AFTER_FAILURES && !self.ejected.swap(true, Ordering::Relaxed) {
+            *self.ejected_since.lock().expect("ejected_since lock poisoned") = Instant::now();
+            self.ejections.fetch_add(1, Ordering::Relaxed);
+            eprintln!("{} failed {failures_in_a_row} times in a row, ejecting it for now... ({reason})", self.endpoint.url.red());
+        }
+    }
+}
+
+
+
+/// sets the combination-proxy servers that all requests get spread across.
+/// if it never gets called it just uses `DEFAULT_REQUEST_SERVER_URL`.
+/// calling it again (even while requesting) replaces all endpoints, their stats and ejections start over.
+pub fn set_request_endpoints(endpoints: Vec<RequestEndpoint>) -> io::Result<()> {
+    if !endpoints.iter().any(|e| e.weight > 0) {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "at least one endpoint needs a weight > 0"));
+    }
+
+    let states: Arc<[EndpointState]> = endpoints.into_iter().map(EndpointState::new).collect();
+    *ENDPOINTS.write().expect("endpoints lock poisoned") = Some(states);
+    Ok(())
+}
+
+fn get_endpoints() -> Arc<[EndpointState]> {
+    if let Some(endpoints) = &*ENDPOINTS.read().expect("endpoints lock poisoned") {
+        return Arc::clone(endpoints);
+    }
+    let mut endpoints = ENDPOINTS.write().expect("endpoints lock poisoned");
+    Arc::clone(endpoints.get_or_insert_with(|| Arc::new([EndpointState::new(RequestEndpoint::new(DEFAULT_REQUEST_SERVER_URL, 1))])))
+}
+
+/// weighted random pick out of all endpoints that are not ejected.
+fn pick_endpoint(endpoints: &[EndpointState]) -> Option<&EndpointState> {
+    let mut available: Vec<&EndpointState> = endpoints.iter()
+        .filter(|e| e.endpoint.weight > 0 && !e.ejected.load(Ordering::Relaxed))
+        .collect();
+
+    // all of them are ejected, try to re-probe one
+    if available.is_empty() {
+        return endpoints.iter().find(|e| e.endpoint.weight > 0 && e.try_available());
+    }
+    // an ejected one is due for a re-probe
+    if let Some(probe) = endpoints.iter().find(|e| e.ejected.load(Ordering::Relaxed) && e.try_available()) {
+        return Some(probe);
+    }
+
+    let total_weight: u64 = available.iter().map(|e| u64::from(e.endpoint.weight)).sum();
+    let mut pick = fastrand::u64(0..total_weight);
+    available.retain(|e| {
+        let weight = u64::from(e.endpoint.weight);
+        let keep = pick < weight;
+        pick = pick.saturating_sub(weight);
+        keep
+    });
+    available.first().copied()
+}
 
 
 #[derive(Debug, Clone)]
@@ -34,6 +173,50 @@ pub struct RequestStats {
     pub to_request: usize,
     pub start_time: Instant,
     pub name: String,
+    pub progress: Arc<Progress>,
+}
+
+
+
+
+/// how pending recipes get ordered before they are requested.
+/// `RecipesState::request_priorities` is applied in order, later priorities only break ties of earlier ones.
+/// (an empty list just requests them sorted by id)
+#[derive(Debug, Clone)]
+pub enum RequestPriority {
+    /// ingredients with a low `get_element_heuristic_map` cost first. (uses the higher cost of both ingredients)
+    HeuristicDepth,
+    /// ingredients with a low explorer depth first, e.g. from `EncounteredElements::depth_map`.
+    /// this way all recipes that block the current explorer depth get requested before the deeper ones.
+    /// (elements that are not in the map count as depth 0, those are the base elements of the explorer)
+    ExplorerDepth(FxHashMap<Element, usize>),
+    /// shorter ingredient names first (length of both names added together)
+    ShortestNames,
+}
+
+
+type RequestScore = Box<[u64]>;
+
+/// a priority queue of pending recipes, pops the lowest score first.
+pub struct RequestQueue {
+    heap: BinaryHeap<Reverse<(RequestScore, (Element, Element))>>,
+}
+
+impl RequestQueue {
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+}
+
+impl Iterator for RequestQueue {
+    type Item = (Element, Element);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.heap.pop().map(|Reverse((_, recipe))| recipe)
+    }
 }
 
 
@@ -48,18 +231,13 @@ pub struct CombineResponse {
     #[serde(rename = "isNew")]
     #[serde(default)]
     pub is_new: Option<bool>,
+    /// the proxy that answered
+    #[serde(skip)]
+    pub endpoint: String,
 }
 
 
 pub async fn combine(first: &str, second: &str) -> Option<CombineResponse> {
-    // Build URL with query parameters
-    let request_url = format!("{}/?first={}&second={}",
-        REQUEST_SERVER_URL,
-        urlencoding::encode(first),
-        urlencoding::encode(second)
-    );
-
-
     let client = CLIENT.get_or_init(|| {
         match reqwest::Client::builder().timeout(COMBINE_TIMEOUT).build() {
             Ok(c) => c,
@@ -70,15 +248,38 @@ pub async fn combine(first: &str, second: &str) -> Option<CombineResponse> {
     let mut attempt = 0;
     let mut connection_failures = 0;
     while attempt < COMBINE_RETRIES {
+        let endpoints = get_endpoints();
+        let Some(endpoint) = pick_endpoint(&endpoints) else {
+            if connection_failures % 10 == 1 {
+                eprintln!("all request endpoints are ejected. Retrying in 10s...");
+            }
+            connection_failures += 1;
+            tokio::time::sleep(tokio::time::Duration::from_secs(10)).await;
+            continue;
+        };
+
+        // Build URL with query parameters
+        let request_url = format!("{}/?first={}&second={}",
+            endpoint.endpoint.url,
+            urlencoding::encode(first),
+            urlencoding::encode(second)
+        );
+
+        endpoint.requests.fetch_add(1, Ordering::Relaxed);
+        let request_time = Instant::now();
+
         // println!("Rust: Sending request to server: {}", request_url);
         let response = match client.get(&request_url).send().await {
             Ok(res) => { res },
             Err(e) => {
-                if connection_failures % 10 == 1 {
-                    eprintln!("{REQUEST_SERVER_URL} is down or unreachable. Retrying in 10s... {e}");
+                endpoint.record_failure(&e.to_string());
+                if endpoints.len() == 1 {
+                    if connection_failures % 10 == 1 {
+                        eprintln!("{} is down or unreachable. Retrying in 10s... {e}", endpoint.endpoint.url);
+                    }
+                    connection_failures += 1;
+                    tokio::time::sleep(tokio::time::Duration::from_secs(10)).await;
                 }
-                connection_failures += 1;
-                tokio::time::sleep(tokio::time::Duration::from_secs(10)).await;
                 continue;
             }
         };
@@ -87,20 +288,27 @@ pub async fn combine(first: &str, second: &str) -> Option<CombineResponse> {
         attempt += 1;
 
         let status = response.status();
-        let response_text = response.text().await.expect("could not get response.text()"); // Get body text
+        let Ok(response_text) = response.text().await else {
+            endpoint.record_failure("could not get response.text()");
+            continue;
+        };
         // println!("Rust: Received status: {}", status);
 
         if status.is_success() {
             // Try parsing as the success response
             match serde_json::from_str::<CombineResponse>(&response_text) {
-                Ok(data) => {
+                Ok(mut data) => {
+                    endpoint.record_success(request_time.elapsed());
+                    data.endpoint = endpoint.endpoint.url.clone();
                     return Some(data);
                 }
                 Err(e) => {
+                    endpoint.record_failure("invalid json");
                     eprintln!("Rust: Failed to parse SUCCESS JSON: {e}. JSON TEXT: {response_text}");
                 },
             }
         } else {
+            endpoint.record_failure(&format!("status {status}"));
             // eprintln!("Rust: Request failed: {status}");
         }
     }
@@ -114,13 +322,66 @@ pub async fn combine(first: &str, second: &str) -> Option<CombineResponse> {
 
 
 impl RecipesState {
+    /// takes all `to_request_recipes` and orders them by `request_priorities`.
+    pub fn take_prioritized_to_request_recipes(&mut self) -> RequestQueue {
+        let start_time = Instant::now();
+        let to_request_recipes = std::mem::take(&mut self.to_request_recipes);
+
+        let heuristic_map = self.request_priorities.iter()
+            .any(|p| matches!(p, RequestPriority::HeuristicDepth))
+            .then(|| self.get_element_heuristic_map(&self.get_recipes_uses_map()));
+
+        let heap = to_request_recipes
+            .into_iter()
+            .map(|recipe| Reverse((self.request_score(recipe, heuristic_map.as_ref()), recipe)))
+            .collect::<BinaryHeap<_>>();
+
+        if !self.request_priorities.is_empty() {
+            println!("Prioritized {} requests by {:?} ({:?})",
+                heap.len().to_formatted_string(&num_format::Locale::en),
+                self.request_priorities.iter().map(RequestPriority::name).collect::<Vec<_>>(),
+                start_time.elapsed()
+            );
+        }
+        RequestQueue { heap }
+    }
+
+
+    fn request_score(&self, (f, s): (Element, Element), heuristic_map: Option<&ElementHeuristicMap>) -> RequestScore {
+        self.request_priorities.iter()
+            .map(|priority| match priority {
+                RequestPriority::HeuristicDepth => {
+                    let heuristic_map = heuristic_map.expect("heuristic_map is always made for HeuristicDepth");
+                    let f_cost = heuristic_map[self.case_id(f) as usize];
+                    let s_cost = heuristic_map[self.case_id(s) as usize];
+                    f_cost.max(s_cost)
+                },
+                RequestPriority::ExplorerDepth(depth_map) => {
+                    let depth = |x: Element| depth_map.get(&x)
+                        .or_else(|| depth_map.get(&self.case_id(x)))
+                        .map_or(0, |&d| d as u64);
+                    depth(f).max(depth(s))
+                },
+                RequestPriority::ShortestNames => {
+                    (self.num_to_str[f as usize].len() + self.num_to_str[s as usize].len()) as u64
+                },
+            })
+            .collect()
+    }
+
+
+
     pub async fn process_all_to_request_recipes(&mut self, name: &str) {
+        cancel::install_ctrl_c_handler();
+        if cancel::is_cancelled() { return; }
+
         let request_stats_arc = Arc::new(Mutex::new(RequestStats {
             to_request: self.to_request_recipes.len(),
             outgoing_requests: 0,
             responded_requests: 0,
             start_time: Instant::now(),
-            name: name.to_string()
+            name: name.to_string(),
+            progress: Progress::new(name, self.to_request_recipes.len()),
         }));
 
         let rs_clone = Arc::clone(&request_stats_arc);
@@ -132,10 +393,15 @@ impl RecipesState {
             }
         });
         
-        let to_request_recipes = std::mem::take(&mut self.to_request_recipes);
+        let mut to_request_recipes = self.take_prioritized_to_request_recipes();
         let num_to_str_clone_arc = Arc::new(self.num_to_str.clone());
 
-        let mut stream = futures::stream::iter(to_request_recipes)
+        // after a Ctrl+C no new requests get started, but the in-flight ones still finish
+        let not_cancelled_recipes = std::iter::from_fn(|| {
+            if cancel::is_cancelled() { None } else { to_request_recipes.next() }
+        });
+
+        let mut stream = futures::stream::iter(not_cancelled_recipes)
             .map(|(f, s)| {
                 let rs_clone = Arc::clone(&request_stats_arc);
                 let num_to_str_clone = Arc::clone(&num_to_str_clone_arc);
@@ -145,10 +411,10 @@ impl RecipesState {
     
                     let first_str = num_to_str_clone[f as usize].clone();
                     let second_str = num_to_str_clone[s as usize].clone();
-                    let result_str = combine(&first_str, &second_str).await
-                        .map_or_else(|| String::from("Nothing"), |res| res.result);
+                    let (result_str, proxy) = combine(&first_str, &second_str).await
+                        .map_or_else(|| (String::from("Nothing"), None), |res| (res.result, Some(res.endpoint)));
     
-                    (first_str, second_str, result_str)
+                    (first_str, second_str, result_str, proxy)
                 })
             })
             // this makes sure that not all tasks are spawned at once, it is limited
@@ -156,39 +422,38 @@ impl RecipesState {
 
         
         let mut str_to_num = self.get_str_to_num_map();
-
-        loop {
-        tokio::select! {
-            // BRANCH 1: a request to local:3000 finished
-            result = stream.next() => {
-                if let Some(task_result) = result {
-                    match task_result {
-                        Ok((first_str, second_str, result_str)) => {
-                            self.variables_add_recipe(&first_str, &second_str, &result_str, &mut str_to_num);
-                        },
-                        Err(join_err) => {
-                            eprintln!("Task panicked or was cancelled: {join_err}");
-                        },
-                    }
-                    request_stats_arc.lock().expect("rs lock poisoned").responded_requests += 1;
-                
-                    self.recipes_updated_total += 1;
-                    if let Some(auto_save) = &self.auto_save
-                    && (self.recipes_updated_total + 1).is_multiple_of(auto_save.every_changed_recipes) {
-                        self.auto_save();
-                    }
-                } else {
-                    // `futures.next()` returned None, meaning all requests are done.
-                    break;
-                }
+        let session_timestamp = provenance::now_millis();
+
+        // `futures.next()` returns None when all requests are done (or canceled and all in-flight ones are done)
+        while let Some(task_result) = stream.next().await {
+            match task_result {
+                Ok((first_str, second_str, result_str, proxy)) => {
+                    self.variables_add_recipe(&first_str, &second_str, &result_str, &mut str_to_num);
+                    self.provenance.set_requested(&first_str, &second_str, &str_to_num, proxy.as_deref(), session_timestamp);
+                },
+                Err(join_err) => {
+                    eprintln!("Task panicked or was cancelled: {join_err}");
+                },
             }
-
-            // BRANCH 2: user pressed ctrl+c
-            _ = tokio::signal::ctrl_c() => {
-                println!("\n[!] Ctrl+C detected! Canceling remaining requests... (it should hopefully autosave in main now.)");
-                return;
+            let mut rs = request_stats_arc.lock().expect("rs lock poisoned");
+            rs.responded_requests += 1;
+            rs.progress.inc(1);
+            drop(rs);
+        
+            self.recipes_updated_total += 1;
+            if let Some(auto_save) = &self.auto_save
+            && (self.recipes_updated_total + 1).is_multiple_of(auto_save.every_changed_recipes) {
+                self.auto_save();
             }
         }
+        drop(stream);
+
+        if cancel::is_cancelled() {
+            // put the remaining ones back, so they can still be extracted or requested later
+            let remaining = to_request_recipes.len();
+            self.to_request_recipes.extend(to_request_recipes);
+            println!("Canceled {} remaining requests. Saving a checkpoint...", remaining.to_formatted_string(&num_format::Locale::en));
+            self.auto_save();
         }
 
         let rs = request_stats_arc.lock().expect("Final lock poisoned");
@@ -201,8 +466,25 @@ impl RecipesState {
 
 
 
+impl RequestPriority {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::HeuristicDepth => "HeuristicDepth",
+            Self::ExplorerDepth(_) => "ExplorerDepth",
+            Self::ShortestNames => "ShortestNames",
+        }
+    }
+}
+
+
+
+
+
 fn interval_message(rs: &RequestStats) {
-    println!("{} Requests: {}/{},  Time: {},  Current Outgoing: {},  Rps: {}",
+    rs.progress.set_counter("outgoing", (rs.outgoing_requests - rs.responded_requests) as u64);
+    let snapshot: ProgressSnapshot = rs.progress.export();
+
+    println!("{} Requests: {}/{},  Time: {},  Current Outgoing: {},  Rps: {},  ETA: {}{}",
         rs.name,
         rs.responded_requests.to_formatted_string(&num_format::Locale::en).green(),
         (rs.to_request).to_formatted_string(&num_format::Locale::en).green(),
@@ -212,5 +494,24 @@ fn interval_message(rs: &RequestStats) {
         (rs.outgoing_requests - rs.responded_requests).to_string().green(),
         
         format!("{:.3}", rs.responded_requests as f64 / rs.start_time.elapsed().as_secs_f64()).green(),
+
+        snapshot.eta_secs.map_or_else(|| "?".to_string(), |eta| format!("{:?}", Duration::from_secs(eta as u64))).yellow(),
+        snapshot.memory_rss_bytes.map_or_else(String::new, |rss| format!(",  Memory: {}MB", (rss / 1024 / 1024).to_string().purple())),
     );
+
+    let endpoints = get_endpoints();
+    if endpoints.len() > 1 {
+        for e in endpoints.iter() {
+            let successes = e.successes.load(Ordering::Relaxed);
+            println!("  - {}{}  Requests: {},  Ok: {},  Failed: {},  Ejected: {}x,  Avg: {}ms",
+                e.endpoint.url,
+                if e.ejected.load(Ordering::Relaxed) { " (ejected)".red() } else { "".normal() },
+                e.requests.load(Ordering::Relaxed).to_formatted_string(&num_format::Locale::en).green(),
+                successes.to_formatted_string(&num_format::Locale::en).green(),
+                e.failures.load(Ordering::Relaxed).to_formatted_string(&num_format::Locale::en).red(),
+                e.ejections.load(Ordering::Relaxed),
+                e.total_response_ms.load(Ordering::Relaxed).checked_div(successes as u64).unwrap_or(0),
+            );
+        }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,173 @@
+use std::{fs::File, io::{self, BufWriter, Write}, time::Instant};
+use futures::stream::StreamExt;
+use num_format::ToFormattedString;
+use colored::Colorize;
+
+use crate::{
+    RECIPE_FILES_FOLDER,
+    cancel,
+    provenance,
+    recipe_requestor::{combine, MAX_CONCURRENT_REQUESTS},
+    structures::{Element, RecipesState, NOTHING_ID, UNKNOWN_ID, neal_case_name},
+};
+
+
+
+/// how `verify_stored_recipes` picks the recipes it re-requests.
+#[derive(Debug, Clone, Copy)]
+pub enum VerifySampling {
+    /// uniformly random over all stored recipes (`=unknown=` results are skipped)
+    Random,
+    /// the same amount of `Nothing` recipes and real recipes.
+    /// there are usually way more real recipes, so this makes sure `Nothing` gets checked too.
+    Stratified,
+}
+
+
+#[derive(Debug, Clone)]
+pub struct VerifyMismatch {
+    pub first: String,
+    pub second: String,
+    pub stored: String,
+    pub server: String,
+    /// stored and server result are the same in neal case (e.g. `Hello world` vs `Hello World`)
+    pub case_only: bool,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct VerifyReport {
+    pub checked: usize,
+    pub matching: usize,
+    /// requests where the server didn't give an answer at all (these are not counted as mismatches)
+    pub failed_requests: usize,
+    pub mismatches: Vec<VerifyMismatch>,
+    pub corrected: usize,
+}
+
+impl VerifyReport {
+    pub fn case_only_mismatches(&self) -> usize {
+        self.mismatches.iter().filter(|m| m.case_only).count()
+    }
+
+    /// writes every mismatch as `first + second = stored -> server` into the `RECIPE_FILES_FOLDER`
+    pub fn write_to_file(&self, file_name: &str) -> io::Result<()> {
+        let file = File::create(format!("{RECIPE_FILES_FOLDER}/{file_name}"))?;
+        let mut writer = BufWriter::new(file);
+
+        writeln!(writer, "checked: {}, matching: {}, failed requests: {}, mismatches: {} ({} case only), corrected: {}\n",
+            self.checked, self.matching, self.failed_requests, self.mismatches.len(), self.case_only_mismatches(), self.corrected
+        )?;
+        for m in &self.mismatches {
+            writeln!(writer, "{} + {} = {} -> {}{}", m.first, m.second, m.stored, m.server, if m.case_only { "  // case only" } else { "" })?;
+        }
+        writer.flush()
+    }
+}
+
+
+
+
+impl RecipesState {
+    /// picks `sample_size` stored recipes and returns them in a reproducible order.
+    /// the same database with the same `seed` always gives the same sample.
+    pub fn sample_stored_recipes(&self, sample_size: usize, sampling: VerifySampling, seed: u64) -> Vec<(Element, Element)> {
+        let mut rng = fastrand::Rng::with_seed(seed);
+
+        let mut sample_from = |mut recipes: Vec<(Element, Element)>, amount: usize| {
+            // sort first, the FxHashMap order depends on how the recipes got inserted
+            recipes.sort_unstable();
+            rng.shuffle(&mut recipes);
+            recipes.truncate(amount);
+            recipes
+        };
+
+        match sampling {
+            VerifySampling::Random => {
+                let recipes = self.recipes_ing.iter()
+                    .filter(|&(_, &r)| r != UNKNOWN_ID)
+                    .map(|(&recipe, _)| recipe)
+                    .collect();
+                sample_from(recipes, sample_size)
+            },
+            VerifySampling::Stratified => {
+                let (nothing, real): (Vec<_>, Vec<_>) = self.recipes_ing.iter()
+                    .filter(|&(_, &r)| r != UNKNOWN_ID)
+                    .partition(|&(_, &r)| r == NOTHING_ID);
+
+                let nothing_amount = (sample_size / 2).min(nothing.len());
+                let mut sample = sample_from(nothing.into_iter().map(|(&recipe, _)| recipe).collect(), nothing_amount);
+                sample.extend(sample_from(real.into_iter().map(|(&recipe, _)| recipe).collect(), sample_size - nothing_amount));
+                sample
+            },
+        }
+    }
+
+
+
+    /// re-requests a sample of stored recipes and compares them with what the server says now.
+    /// with `auto_correct` every mismatch gets overwritten with the server result.
+    pub async fn verify_stored_recipes(&mut self, sample_size: usize, sampling: VerifySampling, seed: u64, auto_correct: bool) -> VerifyReport {
+        let start_time = Instant::now();
+        cancel::install_ctrl_c_handler();
+        let sample = self.sample_stored_recipes(sample_size, sampling, seed);
+        println!("Verifying {} stored recipes ({sampling:?}, seed {seed})...", sample.len().to_formatted_string(&num_format::Locale::en));
+
+        let mut report = VerifyReport::default();
+        let mut str_to_num = if auto_correct { self.get_str_to_num_map() } else { Default::default() };
+        let session_timestamp = provenance::now_millis();
+
+        let requests: Vec<(String, String, String)> = sample.into_iter()
+            .map(|(f, s)| (self.num_to_str_fn(f), self.num_to_str_fn(s), self.num_to_str_fn(self.recipes_ing[&(f, s)])))
+            .collect();
+
+        // after a Ctrl+C no new requests get started, the report only contains what got checked
+        let mut requests = requests.into_iter();
+        let not_cancelled_requests = std::iter::from_fn(|| {
+            if cancel::is_cancelled() { None } else { requests.next() }
+        });
+
+        let mut stream = futures::stream::iter(not_cancelled_requests)
+            .map(|(first, second, stored)| async move {
+                let server = combine(&first, &second).await.map(|res| (res.result, res.endpoint));
+                (first, second, stored, server)
+            })
+            .buffer_unordered(MAX_CONCURRENT_REQUESTS);
+
+        while let Some((first, second, stored, server)) = stream.next().await {
+            report.checked += 1;
+            let Some((server, proxy)) = server else {
+                report.failed_requests += 1;
+                continue;
+            };
+
+            if server == stored {
+                report.matching += 1;
+                continue;
+            }
+
+            let case_only = neal_case_name(&server) == neal_case_name(&stored);
+            println!("  - {} + {} = {} -> {}{}",
+                first, second, stored.red(), server.green(),
+                if case_only { " (case only)" } else { "" }
+            );
+
+            if auto_correct {
+                self.variables_add_recipe(&first, &second, &server, &mut str_to_num);
+                self.provenance.set_requested(&first, &second, &str_to_num, Some(&proxy), session_timestamp);
+                report.corrected += 1;
+            }
+            report.mismatches.push(VerifyMismatch { first, second, stored, server, case_only });
+        }
+
+        println!("Verified {} recipes ({:?}) - matching: {}, mismatches: {} ({} case only), failed requests: {}, corrected: {}",
+            report.checked,
+            start_time.elapsed(),
+            report.matching.to_string().green(),
+            report.mismatches.len().to_string().red(),
+            report.case_only_mismatches().to_string().yellow(),
+            report.failed_requests,
+            report.corrected,
+        );
+        report
+    }
+}
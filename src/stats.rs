@@ -0,0 +1,184 @@
+use serde::Serialize;
+use std::{collections::BTreeMap, fs::File, io::{self, BufWriter, Write}, time::Instant};
+use rustc_hash::FxHashSet;
+use num_format::ToFormattedString;
+use colored::Colorize;
+
+use crate::{RECIPE_FILES_FOLDER, structures::{Element, RecipesState, NOTHING_ID, UNKNOWN_ID}};
+
+
+
+/// `(bucket label, count)`, in bucket order
+pub type Histogram = Vec<(String, usize)>;
+
+
+/// everything `stats` finds out about a database.
+/// element counts only look at neal case elements (without `Nothing` and `=unknown=`),
+/// the same way the result/uses maps see them.
+#[derive(Debug, Clone, Serialize)]
+pub struct RecipesStats {
+    pub elements: usize,
+    pub neal_case_elements: usize,
+    /// elements that are only a different casing of some neal case element
+    pub case_variants: usize,
+
+    pub recipes: usize,
+    pub real_recipes: usize,
+    pub nothing_recipes: usize,
+    pub unknown_recipes: usize,
+
+    pub never_a_result: usize,
+    pub never_an_ingredient: usize,
+
+    /// how many recipes result in an element (log2 buckets)
+    pub in_degree: Histogram,
+    /// how many recipes use an element (log2 buckets)
+    pub out_degree: Histogram,
+    /// `(element, distinct real results)`, most productive first
+    pub top_ingredients: Vec<(String, usize)>,
+    /// name length in chars (buckets of 5)
+    pub name_lengths: Histogram,
+    /// `get_element_heuristic_map` depth, elements it can't reach are in the `unreachable` bucket
+    pub heuristic_depths: Histogram,
+}
+
+
+
+impl RecipesState {
+    /// collects a `RecipesStats` report. `top_n` = how many of the most productive ingredients to list
+    pub fn stats(&self, top_n: usize) -> RecipesStats {
+        let start_time = Instant::now();
+
+        let recipes_result_map = self.get_recipes_result_map();
+        let recipes_uses_map = self.get_recipes_uses_map();
+        let heuristic_map = self.get_element_heuristic_map(&recipes_uses_map);
+
+        let neal_elements: Vec<Element> = (0..self.num_to_str.len() as Element)
+            .filter(|&e| self.neal_case_map[e as usize] == e && e != NOTHING_ID && e != UNKNOWN_ID)
+            .collect();
+
+        let mut nothing_recipes = 0;
+        let mut unknown_recipes = 0;
+        for &r in self.recipes_ing.values() {
+            match r {
+                NOTHING_ID => nothing_recipes += 1,
+                UNKNOWN_ID => unknown_recipes += 1,
+                _ => {},
+            }
+        }
+
+        let mut top_ingredients: Vec<(Element, usize)> = neal_elements.iter()
+            .map(|&e| {
+                let results: FxHashSet<Element> = recipes_uses_map[e as usize].iter()
+                    .map(|&(_, r)| r)
+                    .filter(|&r| r != NOTHING_ID && r != UNKNOWN_ID)
+                    .collect();
+                (e, results.len())
+            })
+            .filter(|&(_, results)| results > 0)
+            .collect();
+        top_ingredients.sort_unstable_by_key(|&(e, results)| (std::cmp::Reverse(results), e));
+        top_ingredients.truncate(top_n);
+
+        let mut name_lengths: BTreeMap<usize, usize> = BTreeMap::new();
+        let mut heuristic_depths: BTreeMap<u64, usize> = BTreeMap::new();
+        for &e in &neal_elements {
+            *name_lengths.entry(self.num_to_str[e as usize].chars().count() / 5).or_default() += 1;
+            *heuristic_depths.entry(heuristic_map[e as usize]).or_default() += 1;
+        }
+
+        let stats = RecipesStats {
+            elements: self.num_to_str.len(),
+            neal_case_elements: neal_elements.len(),
+            case_variants: (0..self.num_to_str.len()).filter(|&e| self.neal_case_map[e] as usize != e).count(),
+
+            recipes: self.recipes_ing.len(),
+            real_recipes: self.recipes_ing.len() - nothing_recipes - unknown_recipes,
+            nothing_recipes,
+            unknown_recipes,
+
+            never_a_result: neal_elements.iter().filter(|&&e| recipes_result_map[e as usize].is_empty()).count(),
+            never_an_ingredient: neal_elements.iter().filter(|&&e| recipes_uses_map[e as usize].is_empty()).count(),
+
+            in_degree: log2_histogram(neal_elements.iter().map(|&e| recipes_result_map[e as usize].len())),
+            out_degree: log2_histogram(neal_elements.iter().map(|&e| recipes_uses_map[e as usize].len())),
+            top_ingredients: top_ingredients.into_iter()
+                .map(|(e, results)| (self.num_to_str_fn(e), results))
+                .collect(),
+            name_lengths: name_lengths.into_iter()
+                .map(|(bucket, count)| (format!("{}-{}", bucket * 5, bucket * 5 + 4), count))
+                .collect(),
+            heuristic_depths: heuristic_depths.into_iter()
+                .map(|(depth, count)| (if depth == u64::MAX { "unreachable".to_string() } else { depth.to_string() }, count))
+                .collect(),
+        };
+        println!("Collected stats in {:?}", start_time.elapsed());
+        stats
+    }
+}
+
+
+
+/// buckets: `0`, `1`, `2-3`, `4-7`, `8-15`, ...
+fn log2_histogram(values: impl Iterator<Item = usize>) -> Histogram {
+    let mut buckets: BTreeMap<u32, usize> = BTreeMap::new();
+    for value in values {
+        let bucket = if value == 0 { 0 } else { value.ilog2() + 1 };
+        *buckets.entry(bucket).or_default() += 1;
+    }
+    buckets.into_iter()
+        .map(|(bucket, count)| {
+            let label = match bucket {
+                0 => "0".to_string(),
+                1 => "1".to_string(),
+                _ => format!("{}-{}", 1usize << (bucket - 1), (1usize << bucket) - 1),
+            };
+            (label, count)
+        })
+        .collect()
+}
+
+
+
+
+impl RecipesStats {
+    pub fn print_table(&self) {
+        let fmt = |x: usize| x.to_formatted_string(&num_format::Locale::en);
+        let row = |name: &str, value: usize| println!("  {name:<24} {:>14}", fmt(value));
+
+        println!("{}", "Database stats".purple());
+        row("elements", self.elements);
+        row("neal case elements", self.neal_case_elements);
+        row("case variants", self.case_variants);
+        row("recipes", self.recipes);
+        row("  real", self.real_recipes);
+        row("  Nothing", self.nothing_recipes);
+        row("  =unknown=", self.unknown_recipes);
+        row("never a result", self.never_a_result);
+        row("never an ingredient", self.never_an_ingredient);
+
+        let histogram = |name: &str, histogram: &Histogram| {
+            println!("{}", name.purple());
+            for (label, count) in histogram {
+                row(label, *count);
+            }
+        };
+        histogram("in degree (recipes resulting in it)", &self.in_degree);
+        histogram("out degree (recipes using it)", &self.out_degree);
+        histogram("name length", &self.name_lengths);
+        histogram("heuristic depth", &self.heuristic_depths);
+
+        println!("{}", "top ingredients (distinct real results)".purple());
+        for (name, results) in &self.top_ingredients {
+            println!("  {name:<24} {:>14}", fmt(*results));
+        }
+    }
+
+    /// writes the stats as pretty json into the `RECIPE_FILES_FOLDER`
+    pub fn write_json(&self, file_name: &str) -> io::Result<()> {
+        let file = File::create(format!("{RECIPE_FILES_FOLDER}/{file_name}"))?;
+        let mut writer = BufWriter::new(file);
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writer.flush()
+    }
+}
@@ -1,3 +1,4 @@
+use serde::{Deserialize, Serialize};
 use std::{cmp::Reverse, collections::BinaryHeap, fs::File, io::{self, BufWriter}, time::Instant};
 use dashmap::DashSet;
 use num_format::ToFormattedString;
@@ -6,8 +7,15 @@ use rustc_hash::{FxHashMap, FxHashSet};
 use std::io::Write;
 use colored::Colorize;
 
+use crate::cancel;
+pub use crate::normalization::{start_case_unicode, neal_case_name};
 use crate::lineage::LineageStep;
 use crate::recipe_loader::RecipesFile;
+use crate::recipe_merge::MergeState;
+use crate::provenance::Provenance;
+use crate::dead_elements::DeadElements;
+use crate::name_hygiene::NameHygiene;
+use crate::recipe_requestor::RequestPriority;
 
 
 
@@ -33,15 +41,29 @@ pub struct RecipesState {
     pub recipes_ing: FxHashMap<(u32, u32), u32>,
 
     pub to_request_recipes: DashSet<(u32, u32)>,
+    /// the order in which `to_request_recipes` get requested (see `RequestPriority`)
+    pub request_priorities: Vec<RequestPriority>,
     pub auto_save: Option<AutoSaveRecipes>,
-    pub recipes_updated_total: usize
+    pub recipes_updated_total: usize,
+    /// merge policy, conflicts and which file the recipes came from (see `set_merge_policy`)
+    pub merging: MergeState,
+    /// where every recipe came from (see `track_provenance`)
+    pub provenance: Provenance,
+    /// which elements the explorers ban (see `classify_dead_elements`)
+    pub dead_elements: DeadElements,
+    /// which case variants are the same element (see `case_id`)
+    pub case_mode: CaseMode,
+    /// what `load` does with names that only differ by invisible stuff (see `clean_element_names`)
+    pub name_hygiene: NameHygiene,
 }
 
 #[derive(Debug, Clone)]
 pub struct AutoSaveRecipes {
     pub every_changed_recipes: usize,
     pub file_name: String,
-    pub file_mode: RecipesFile
+    pub file_mode: RecipesFile,
+    /// `recipes_updated_total` at the last autosave
+    pub saved_at_updated_total: Option<usize>,
 }
 
 impl RecipesState {
@@ -52,8 +74,14 @@ impl RecipesState {
             neal_case_map: (0..HARDCODED_IDS.len() as u32).collect(),
             recipes_ing: FxHashMap::default(),
             to_request_recipes: DashSet::new(),
+            request_priorities: Vec::new(),
             auto_save: None,
-            recipes_updated_total: 0
+            recipes_updated_total: 0,
+            merging: MergeState::default(),
+            provenance: Provenance::default(),
+            dead_elements: DeadElements::default(),
+            case_mode: CaseMode::default(),
+            name_hygiene: NameHygiene::default(),
         };
         state.verify_recipe_stuff().unwrap();
         state
@@ -64,28 +92,45 @@ impl RecipesState {
         s.auto_save = Some(AutoSaveRecipes {
             every_changed_recipes,
             file_name: file_name.to_string(),
-            file_mode
+            file_mode,
+            saved_at_updated_total: None,
         });
         s
     }
 
-    pub fn with_autoload_and_save(file_name: &str, file_mode: RecipesFile, every_changed_recipes: usize) -> Self {
-        let mut s = Self::with_autosave(file_name, file_mode, every_changed_recipes);
-        s.load(file_name, file_mode).unwrap();
-        s
+    /// autosave only gets turned on once the file loaded, so a failed (or Ctrl+C'd) load never overwrites it
+    pub fn with_autoload_and_save(file_name: &str, file_mode: RecipesFile, every_changed_recipes: usize) -> io::Result<Self> {
+        let mut s = Self::without_autosave();
+        s.load(file_name, file_mode)?;
+        s.auto_save = Some(AutoSaveRecipes {
+            every_changed_recipes,
+            file_name: file_name.to_string(),
+            file_mode,
+            // what's in memory right now is exactly the file
+            saved_at_updated_total: Some(s.recipes_updated_total),
+        });
+        Ok(s)
     }
 
-    pub fn auto_save(&self) {
+    pub fn auto_save(&mut self) {
         if let Some(auto_save) = &self.auto_save {
-            self.save(&auto_save.file_name, auto_save.file_mode).unwrap_or_else(
-                |e| println!("could not save to {}... {e} ({})", auto_save.file_name, self.pretty_element_and_recipe_count())
-            );
+            match self.save(&auto_save.file_name, auto_save.file_mode) {
+                Ok(()) => if let Some(auto_save) = &mut self.auto_save {
+                    auto_save.saved_at_updated_total = Some(self.recipes_updated_total);
+                },
+                Err(e) => println!("could not save to {}... {e} ({})", auto_save.file_name, self.pretty_element_and_recipe_count()),
+            }
         }
     }
 }
 
 impl Drop for RecipesState {
     fn drop(&mut self) {
+        // after a Ctrl+C the checkpoint was already saved, don't save the exact same thing again
+        let already_saved = self.auto_save.as_ref()
+            .is_some_and(|a| a.saved_at_updated_total == Some(self.recipes_updated_total));
+        if cancel::is_cancelled() && already_saved { return; }
+
         self.auto_save();
     }
 }
@@ -96,6 +141,18 @@ impl Drop for RecipesState {
 pub type Element = u32;
 
 
+/// how case variants (`Hello world` / `Hello World`) are treated by the explorers, the result/uses maps and lineages
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CaseMode {
+    /// every case variant is its `neal_case_name` (neal case) element
+    #[default]
+    Neal,
+    /// case variants are different elements, like in the actual game
+    Exact,
+}
+
+
 pub type RecipesResultICMap = Vec<Vec<(Element, Element)>>;
 pub type RecipesUsesICMap = Vec<Vec<(Element, Element)>>;
 pub type ElementHeuristicMap = Vec<u64>;
@@ -104,36 +161,6 @@ pub type ElementHeuristicMap = Vec<u64>;
 
 
 
-pub fn start_case_unicode(input: &str) -> String {
-    if input.is_empty() { return String::new(); }
-    // Pre-allocate, but be aware capacity might be insufficient if chars expand (e.g., ß -> SS)
-    let mut result = String::with_capacity(input.len());
-    let mut capitalize_next = true;
-
-    for c in input.chars() {
-        if c.is_ascii_whitespace() {
-            result.push(' ');
-            capitalize_next = true;
-        } else if capitalize_next {
-            // to_uppercase returns an iterator
-            for uppercased in c.to_uppercase() {
-                result.push(uppercased);
-            }
-            capitalize_next = false;
-        } else {
-            // to_lowercase returns an iterator
-            for lowercased in c.to_lowercase() {
-                result.push(lowercased);
-            }
-            // capitalize_next remains false
-        }
-    }
-    // Optional: Reclaim potentially over-allocated memory if string shrank or didn't grow much
-    // result.shrink_to_fit();
-    result
-}
-
-
 pub const fn sort_recipe_tuple(tup: (u32, u32)) -> (u32, u32) {
     let (a, b) = tup;
     if a <= b { tup }
@@ -144,6 +171,24 @@ pub const fn sort_recipe_tuple(tup: (u32, u32)) -> (u32, u32) {
 
 
 impl RecipesState {
+    /// the element that `element` counts as with the current `case_mode`
+    /// (its neal case element, or itself for `CaseMode::Exact`)
+    pub fn case_id(&self, element: Element) -> Element {
+        match self.case_mode {
+            CaseMode::Neal => self.neal_case_map[element as usize],
+            CaseMode::Exact => element,
+        }
+    }
+
+    /// the name that `name` counts as with the current `case_mode`, like `case_id` but for names
+    /// (its `neal_case_name`, or itself for `CaseMode::Exact`)
+    pub fn case_name(&self, name: &str) -> String {
+        match self.case_mode {
+            CaseMode::Neal => neal_case_name(name),
+            CaseMode::Exact => name.to_string(),
+        }
+    }
+
     pub fn num_to_str_fn(&self, num: u32) -> String {
         self.num_to_str[num as usize].clone()
     }
@@ -211,7 +256,7 @@ impl RecipesState {
 
             self.neal_case_map.push(0);  // immidiately push to reserve a spot
 
-            let neal_str = start_case_unicode(element_str);
+            let neal_str = neal_case_name(element_str);
             let neal_id = self.variables_add_element_str(&neal_str, str_to_num);
             
             self.neal_case_map[id as usize] = neal_id;
@@ -287,10 +332,8 @@ impl RecipesState {
 
 
 
-    pub fn find_and_write_dead_elements(&self, output_file_path: &str) -> io::Result<()> {
-        println!("Finding dead elements...");
-        let start_time = std::time::Instant::now();
-    
+    /// dead elements are elements that are used as an ingredient, but only ever result in `Nothing`
+    pub fn find_dead_elements(&self) -> Vec<Element> {
         // 1. In a single parallel pass, identify "live" ingredients and all ingredients.
         let (live_elements, used_ingredients): (FxHashSet<Element>, FxHashSet<Element>) = self.recipes_ing
             .par_iter()
@@ -316,10 +359,22 @@ impl RecipesState {
             );
     
         // 2. The dead elements are those used but not live.
-        let mut dead_element_names: Vec<String> = used_ingredients
+        let mut dead_elements: Vec<Element> = used_ingredients
             .par_iter()
             .filter(|elem| !live_elements.contains(elem))
-            .map(|&elem| self.num_to_str_fn(elem))
+            .copied()
+            .collect();
+        dead_elements.par_sort_unstable();
+        dead_elements
+    }
+
+    pub fn find_and_write_dead_elements(&self, output_file_path: &str) -> io::Result<()> {
+        println!("Finding dead elements...");
+        let start_time = std::time::Instant::now();
+
+        let mut dead_element_names: Vec<String> = self.find_dead_elements()
+            .into_iter()
+            .map(|elem| self.num_to_str_fn(elem))
             .collect();
     
         println!("Found {} dead elements in {:?}.", dead_element_names.len(), start_time.elapsed());
@@ -358,7 +413,13 @@ impl RecipesState {
                 None => line.trim(),
             })
             .filter(|trimmed| !trimmed.is_empty())
-            .map(|elem| self.variables_add_element_str(&start_case_unicode(elem), &mut str_to_num))
+            .map(|elem| {
+                let case_elem = self.case_name(elem);
+                if !str_to_num.contains_key(&case_elem) {
+                    println!("{}", format!("[!] '{elem}' does not exist yet (typo?), did you mean: {:?}", self.did_you_mean(elem)).yellow());
+                }
+                self.variables_add_element_str(&case_elem, &mut str_to_num)
+            })
         );
         elems
     }
@@ -376,9 +437,9 @@ impl RecipesState {
         recipes_result_ic_map.resize(self.num_to_str.len(), Vec::new());
     
         for (&(f, s), &r) in &self.recipes_ing {
-            let f_ic = self.neal_case_map[f as usize];
-            let s_ic = self.neal_case_map[s as usize];
-            let r_ic = self.neal_case_map[r as usize];
+            let f_ic = self.case_id(f);
+            let s_ic = self.case_id(s);
+            let r_ic = self.case_id(r);
     
             recipes_result_ic_map[r_ic as usize].push((f_ic, s_ic));
         };
@@ -397,9 +458,9 @@ impl RecipesState {
         recipes_uses_map.resize(self.num_to_str.len(), Vec::new());
     
         for (&(f, s), &r) in &self.recipes_ing {
-            let f_ic = self.neal_case_map[f as usize];
-            let s_ic = self.neal_case_map[s as usize];
-            let r_ic = self.neal_case_map[r as usize];
+            let f_ic = self.case_id(f);
+            let s_ic = self.case_id(s);
+            let r_ic = self.case_id(r);
     
             recipes_uses_map[f_ic as usize].push((s_ic, r_ic));
             recipes_uses_map[s_ic as usize].push((f_ic, r_ic));
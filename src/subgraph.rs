@@ -0,0 +1,93 @@
+//! extracting only the recipes that matter for some elements into a new (small) `RecipesState`, e.g. to share
+//! "everything within 9 steps of the Alphabet seed" or "everything that leads to Punctuation".
+//! the new state can be saved in any `RecipesFile` format.
+
+use std::time::Instant;
+use rustc_hash::FxHashMap;
+use colored::Colorize;
+
+use crate::{layer_explorer::LayerExplorer, structures::{Element, RecipesState, NOTHING_ID, sort_recipe_tuple}};
+
+
+
+impl RecipesState {
+    /// every recipe between elements that are at most `max_steps` away from `start_elements` (found by the `LayerExplorer`),
+    /// that either results in one of those elements or in `Nothing`.
+    /// (so exploring the new state from `start_elements` finds the same elements, as long as it bans the same dead elements).
+    /// missing recipes the explorer runs into don't end up in `to_request_recipes`, extracting doesn't change `self`
+    pub fn extract_within_steps(&self, start_elements: &[Element], max_steps: usize) -> RecipesState {
+        // the explorer queues missing recipes, put back only the ones that were pending before
+        let pending: Vec<(Element, Element)> = self.to_request_recipes.iter().map(|recipe| *recipe).collect();
+        let encountered = LayerExplorer::start(self, start_elements, max_steps, true, false);
+        self.to_request_recipes.clear();
+        for recipe in pending {
+            self.to_request_recipes.insert(recipe);
+        }
+
+        let mut reachable = vec![false; self.num_to_str.len()];
+        for &e in start_elements.iter().chain(encountered.elements.keys()) {
+            reachable[self.case_id(e) as usize] = true;
+        }
+        let is_reachable = |e: Element| reachable[self.case_id(e) as usize];
+
+        self.extract_recipes(&format!("{max_steps} steps from {:?}", self.num_to_strs_fn(start_elements)), |(f, s), r| {
+            is_reachable(f) && is_reachable(s) && (r == NOTHING_ID || is_reachable(r))
+        })
+    }
+
+
+    /// every recipe that results in one of the `goals` or in one of their ancestors
+    /// (everything that can be used on the way to a goal, following `get_recipes_result_map` back to the base elements)
+    pub fn extract_ancestors(&self, goals: &[Element]) -> RecipesState {
+        let recipes_result_map = self.get_recipes_result_map();
+
+        let mut is_ancestor = vec![false; self.num_to_str.len()];
+        let mut queue: Vec<Element> = goals.iter().map(|&e| self.case_id(e)).collect();
+        for &e in &queue {
+            is_ancestor[e as usize] = true;
+        }
+        while let Some(e) = queue.pop() {
+            for &(f, s) in &recipes_result_map[e as usize] {
+                for ingredient in [f, s] {
+                    if !is_ancestor[ingredient as usize] {
+                        is_ancestor[ingredient as usize] = true;
+                        queue.push(ingredient);
+                    }
+                }
+            }
+        }
+
+        self.extract_recipes(&format!("ancestors of {:?}", self.num_to_strs_fn(goals)), |_, r| is_ancestor[self.case_id(r) as usize])
+    }
+
+
+    /// a new state with only the recipes that `keep` wants (and only the elements they use).
+    /// keeps the exact names, the case mode and the recipe sources (if they're tracked)
+    fn extract_recipes(&self, description: &str, keep: impl Fn((Element, Element), Element) -> bool) -> RecipesState {
+        let start_time = Instant::now();
+        let mut new_state = RecipesState::without_autosave();
+        new_state.case_mode = self.case_mode;
+        if self.provenance.is_tracking() {
+            new_state.track_provenance();
+            new_state.provenance.sources = self.provenance.sources.clone();
+        }
+
+        let mut str_to_num: FxHashMap<String, Element> = new_state.get_str_to_num_map();
+        for (&(f, s), &r) in &self.recipes_ing {
+            if !keep((f, s), r) { continue; }
+
+            let new_f = new_state.variables_add_element_str(&self.num_to_str[f as usize], &mut str_to_num);
+            let new_s = new_state.variables_add_element_str(&self.num_to_str[s as usize], &mut str_to_num);
+            let new_r = new_state.variables_add_element_str(&self.num_to_str[r as usize], &mut str_to_num);
+
+            let recipe = sort_recipe_tuple((new_f, new_s));
+            new_state.recipes_ing.insert(recipe, new_r);
+            if let Some(source) = self.provenance.recipe_source((f, s)) {
+                new_state.provenance.set_recipe_source(recipe, source);
+            }
+        }
+
+        println!("Extracted {} ({}) in {:?}", description.purple(), new_state.pretty_element_and_recipe_count(), start_time.elapsed());
+        new_state
+    }
+}
@@ -0,0 +1,248 @@
+//! the whole table of `start_case_unicode` cases (see the `normalization` module docs for the rules)
+
+use infinite_craft_shenanigance::{RecipesState, start_case_unicode, neal_case_name};
+use infinite_craft_shenanigance::normalization::is_neal_case;
+
+
+
+/// `(input, game case)`
+const CASES: &[(&str, &str)] = &[
+    // plain ascii
+    ("", ""),
+    ("x", "X"),
+    ("water", "Water"),
+    ("WATER", "Water"),
+    ("wAtEr", "Water"),
+    ("steam engine", "Steam Engine"),
+    ("STEAM ENGINE", "Steam Engine"),
+    ("Nothing", "Nothing"),
+    ("=unknown=", "=unknown="),
+
+    // whitespace: ascii whitespace ends a word and becomes a space, nothing gets trimmed or collapsed
+    (" ", " "),
+    ("  leading", "  Leading"),
+    ("trailing  ", "Trailing  "),
+    ("double  space", "Double  Space"),
+    ("tab\tseparated", "Tab Separated"),
+    ("new\nline", "New Line"),
+    ("carriage\r\nreturn", "Carriage  Return"),
+    ("form\x0Cfeed", "Form Feed"),
+    ("vertical\x0Btab", "Vertical\x0Btab"),
+    ("no\u{A0}break space", "No\u{A0}break Space"),
+    ("ideographic\u{3000}space", "Ideographic\u{3000}space"),
+    ("em\u{2003}space", "Em\u{2003}space"),
+    ("zero\u{200B}width", "Zero\u{200B}width"),
+
+    // punctuation doesn't end a word
+    ("jack-o-lantern", "Jack-o-lantern"),
+    ("SPIDER-MAN", "Spider-man"),
+    ("rock 'n' roll", "Rock 'n' Roll"),
+    ("don't", "Don't"),
+    ("O'NEIL", "O'neil"),
+    ("\"quoted\"", "\"quoted\""),
+    ("\"QUOTED\" word", "\"quoted\" Word"),
+    ("'single'", "'single'"),
+    ("\u{201C}curly\u{201D}", "\u{201C}curly\u{201D}"),
+    ("(bracket)", "(bracket)"),
+    ("[TAG] name", "[tag] Name"),
+    ("mr. smith", "Mr. Smith"),
+    ("a.b.c", "A.b.c"),
+    ("hello,world", "Hello,world"),
+    ("AC/DC", "Ac/dc"),
+    ("under_score", "Under_score"),
+    ("#hashtag", "#hashtag"),
+    ("@USER", "@user"),
+
+    // digits and symbols
+    ("3d printer", "3d Printer"),
+    ("3D", "3d"),
+    ("R2-D2", "R2-d2"),
+    ("100%", "100%"),
+    ("e=mc²", "E=mc²"),
+    ("½ life", "½ Life"),
+
+    // emojis
+    ("🔥 fire", "🔥 Fire"),
+    ("🔥FIRE", "🔥fire"),
+    ("👨‍👩‍👧 FAMILY", "👨‍👩‍👧 Family"),
+
+    // latin with diacritics, precomposed and decomposed
+    ("éclair", "Éclair"),
+    ("ÉCLAIR", "Éclair"),
+    ("e\u{301}clair", "E\u{301}clair"),
+    ("E\u{301}CLAIR", "E\u{301}clair"),
+    ("ǰ", "J\u{30C}"),
+    ("ÅNGSTRÖM", "Ångström"),
+    ("øresund", "Øresund"),
+
+    // expanding uppercase: the whole expansion is uppercase (so these aren't neal case yet, see `NEAL_CASES`)
+    ("ß", "SS"),
+    ("ßa", "SSa"),
+    ("SSa", "Ssa"),
+    ("straße", "Straße"),
+    ("STRASSE", "Strasse"),
+    ("ẞ", "ẞ"),
+    ("aẞ", "Aß"),
+    ("ﬁre", "FIre"),
+    ("oﬃce", "Oﬃce"),
+    ("ŉ", "ʼN"),
+
+    // titlecase digraphs
+    ("ǆungla", "Ǆungla"),
+    ("ǅUNGLA", "Ǆungla"),
+    ("aǄ", "Aǆ"),
+    ("ǉubljana", "Ǉubljana"),
+
+    // turkish i, no locale
+    ("istanbul", "Istanbul"),
+    ("İstanbul", "İstanbul"),
+    ("aİ", "Ai\u{307}"),
+    ("ıi", "Ii"),
+    ("ISTANBUL", "Istanbul"),
+
+    // greek, no final sigma
+    ("ΣΟΦΙΑ", "Σοφια"),
+    ("ΟΔΟΣ", "Οδοσ"),
+    ("οδος", "Οδος"),
+    ("ΐ", "\u{399}\u{308}\u{301}"),
+
+    // cyrillic
+    ("москва", "Москва"),
+    ("МОСКВА СИТИ", "Москва Сити"),
+
+    // scripts without case
+    ("東京 tower", "東京 Tower"),
+    ("東京", "東京"),
+    ("مرحبا", "مرحبا"),
+    ("שלום WORLD", "שלום World"),
+    ("ʻokina", "ʻokina"),
+
+    // other letters with case
+    ("ｆｕｌｌ ｗｉｄｔｈ", "Ｆｕｌｌ Ｗｉｄｔｈ"),
+    ("ⓐⒷ", "Ⓐⓑ"),
+    ("ⅻ", "Ⅻ"),
+    ("ǝ", "Ǝ"),
+];
+
+
+
+/// `(input, neal case)` for the ones where it isn't the game case
+const NEAL_CASES: &[(&str, &str)] = &[
+    ("ß", "Ss"),
+    ("ßa", "Ssa"),
+    ("SSa", "Ssa"),
+    ("ssa", "Ssa"),
+    ("ﬁre", "Fire"),
+    ("FIre", "Fire"),
+    ("ŉ", "ʼn"),
+    ("ß ß", "Ss Ss"),
+];
+
+
+
+#[test]
+fn case_table() {
+    let failures: Vec<String> = CASES.iter()
+        .filter(|(input, expected)| start_case_unicode(input) != *expected)
+        .map(|(input, expected)| format!("{input:?}: expected {expected:?}, got {:?}", start_case_unicode(input)))
+        .collect();
+    assert!(failures.is_empty(), "{} wrong:\n{}", failures.len(), failures.join("\n"));
+}
+
+
+#[test]
+fn neal_case_table() {
+    for (input, expected) in NEAL_CASES {
+        assert_eq!(neal_case_name(input), *expected, "{input:?}");
+    }
+}
+
+
+#[test]
+fn neal_case_is_a_fixed_point() {
+    for (input, _) in CASES.iter().chain(NEAL_CASES) {
+        let neal = neal_case_name(input);
+        assert_eq!(start_case_unicode(&neal), neal, "not a fixed point for {input:?}");
+        assert_eq!(neal_case_name(&start_case_unicode(input)), neal, "game case of {input:?} has another neal case");
+        assert!(is_neal_case(&neal));
+    }
+}
+
+
+#[test]
+fn chains_end_within_three_rounds() {
+    for c in (char::MIN..=char::MAX).filter(|c| !c.is_ascii_whitespace()) {
+        for input in [c.to_string(), format!("{c}{c}a")] {
+            let names: Vec<String> = std::iter::successors(Some(input.clone()), |name| Some(start_case_unicode(name)))
+                .take(5)
+                .collect();
+            let rounds = names.windows(2).position(|w| w[0] == w[1]);
+            assert!(rounds.is_some_and(|r| r <= 3), "{input:?} needs more than 3 rounds");
+        }
+    }
+}
+
+
+#[test]
+fn word_boundaries_stay_in_place() {
+    for (input, _) in CASES {
+        let boundaries = |s: &str| -> Vec<usize> {
+            s.chars().enumerate().filter(|(_, c)| c.is_ascii_whitespace()).map(|(i, _)| i).collect()
+        };
+        // only expanding first chars change the char count, none of the cases have one before a space
+        if start_case_unicode(input).chars().count() == input.chars().count() {
+            assert_eq!(boundaries(input), boundaries(&start_case_unicode(input)), "moved a word boundary in {input:?}");
+        }
+    }
+}
+
+
+
+
+#[test]
+fn recipes_of_case_variants_share_a_neal_case_element() {
+    let mut state = RecipesState::without_autosave();
+    let mut str_to_num = state.get_str_to_num_map();
+    state.variables_add_recipe("Water", "Fire", "steam", &mut str_to_num);
+    state.variables_add_recipe("Water", "Water", "STEAM", &mut str_to_num);
+    state.variables_add_recipe("Fire", "Fire", "ßa", &mut str_to_num);
+
+    assert_eq!(state.neal_case_map[str_to_num["steam"] as usize], str_to_num["Steam"]);
+    assert_eq!(state.neal_case_map[str_to_num["STEAM"] as usize], str_to_num["Steam"]);
+    state.variables_add_recipe("Fire", "Fire", "ßa", &mut str_to_num);
+
+    // `ßa` -> `SSa` -> `Ssa`, the map skips the middle
+    assert_eq!(state.neal_case_map[str_to_num["ßa"] as usize], str_to_num["Ssa"]);
+    assert!(!str_to_num.contains_key("SSa"));
+    assert!(state.neal_case_map.iter().all(|&neal| state.neal_case_map[neal as usize] == neal));
+    assert!(state.neal_case_changes().moved.is_empty());
+}
+
+
+#[test]
+fn recanonicalize_fixes_old_chains() {
+    let mut state = RecipesState::without_autosave();
+    // a map with a chain: `ßa` -> `SSa` -> `Ssa`, but `SSa` was linked to itself
+    state.num_to_str.extend(["ßa".to_string(), "SSa".to_string(), "Fire Truck".to_string()]);
+    let (old, ss, truck) = (6, 7, 8);
+    state.neal_case_map.extend([ss, ss, truck]);
+
+    let dry_run = state.neal_case_changes();
+    assert_eq!(dry_run.added, vec!["Ssa".to_string()]);
+    assert_eq!(state.num_to_str.len(), 9);
+
+    let report = state.recanonicalize();
+    assert_eq!(report.added, dry_run.added);
+    let mut moved: Vec<(&str, &str, &str)> = report.moved.iter()
+        .map(|m| (m.element.as_str(), m.old_neal.as_str(), m.new_neal.as_str()))
+        .collect();
+    moved.sort_unstable();
+    assert_eq!(moved, vec![("SSa", "SSa", "Ssa"), ("ßa", "SSa", "Ssa")]);
+
+    let neal = state.get_str_to_num_map()["Ssa"];
+    assert_eq!(state.neal_case_map[old as usize], neal);
+    assert_eq!(state.neal_case_map[ss as usize], neal);
+    assert_eq!(state.neal_case_map[neal as usize], neal);
+    assert_eq!(state.neal_case_map[truck as usize], truck);
+    assert!(state.neal_case_changes().moved.is_empty());
+}
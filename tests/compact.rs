@@ -0,0 +1,85 @@
+//! `compact` has to rewrite every id in the state, and tell callers where the old ids went
+
+use std::collections::BTreeSet;
+use rustc_hash::FxHashMap;
+use infinite_craft_shenanigance::{Element, HARDCODED_IDS, RecipesState, sort_recipe_tuple};
+use infinite_craft_shenanigance::compact::compacted_id;
+use infinite_craft_shenanigance::dead_elements::{DeadConfidence, DeadEvidence};
+use infinite_craft_shenanigance::provenance::RecipeSource;
+use infinite_craft_shenanigance::recipe_requestor::RequestPriority;
+
+
+
+fn named_recipes(state: &RecipesState) -> BTreeSet<[String; 3]> {
+    state.recipes_ing.iter()
+        .map(|(&(f, s), &r)| [f, s, r].map(|e| state.num_to_str[e as usize].clone()))
+        .collect()
+}
+
+
+
+#[test]
+fn compact_keeps_used_elements_in_order() {
+    let mut state = RecipesState::without_autosave();
+    state.track_provenance();
+    state.provenance.sources = vec![RecipeSource { name: "old.json".to_string(), proxy: None, timestamp: 1 }];
+
+    let mut str_to_num = state.get_str_to_num_map();
+    let orphan = state.variables_add_element_str("Orphan", &mut str_to_num);
+    // `steam` brings `Steam` as its neal case element, which is only used through that link
+    state.variables_add_recipe("Water", "Fire", "steam", &mut str_to_num);
+    state.variables_add_recipe("Water", "Earth", "Mud", &mut str_to_num);
+    let unused = state.variables_add_element_str("Unused", &mut str_to_num);
+    let lava = state.variables_add_element_str("lava", &mut str_to_num);
+    let [water, fire, mud] = ["Water", "Fire", "Mud"].map(|name| str_to_num[name]);
+    state.to_request_recipes.insert(sort_recipe_tuple((mud, lava)));
+    state.provenance.recipe_sources.as_mut().unwrap().insert((water, fire), 0);
+
+    let old_len = state.num_to_str.len();
+    // one more than there are elements, e.g. classified on another state
+    state.dead_elements.evidence = vec![DeadEvidence::default(); old_len + 1];
+    state.dead_elements.evidence[mud as usize] = DeadEvidence { confidence: DeadConfidence::Low, tested: 1, nothing: 1 };
+    let depths: FxHashMap<Element, usize> = [(mud, 1), (orphan, 2)].into_iter().collect();
+    state.request_priorities.push(RequestPriority::ExplorerDepth(depths));
+
+    let recipes_before = named_recipes(&state);
+    let names_before = state.num_to_str.clone();
+    let id_map = state.compact();
+
+    // hardcoded ids stay, dropped ones are gone
+    assert_eq!(id_map.len(), old_len);
+    for e in 0..HARDCODED_IDS.len() as Element {
+        assert_eq!(compacted_id(&id_map, e), Some(e));
+    }
+    assert_eq!(compacted_id(&id_map, orphan), None);
+    assert_eq!(compacted_id(&id_map, unused), None);
+    assert_eq!(compacted_id(&id_map, old_len as Element), None);
+
+    // kept elements keep their order and their names
+    let kept: Vec<String> = names_before.iter().filter(|name| *name != "Orphan" && *name != "Unused").cloned().collect();
+    assert_eq!(state.num_to_str, kept);
+    for (old, name) in names_before.iter().enumerate() {
+        if let Some(new) = compacted_id(&id_map, old as Element) {
+            assert_eq!(&state.num_to_str[new as usize], name);
+        }
+    }
+
+    // neal links point to kept ids
+    let str_to_num = state.get_str_to_num_map();
+    assert_eq!(state.neal_case_map.len(), state.num_to_str.len());
+    assert!(state.neal_case_map.iter().all(|&neal| state.neal_case_map[neal as usize] == neal));
+    assert_eq!(state.neal_case_map[str_to_num["steam"] as usize], str_to_num["Steam"]);
+    assert_eq!(state.neal_case_map[str_to_num["lava"] as usize], str_to_num["Lava"]);
+
+    // everything that holds ids
+    let new_id = |old: Element| compacted_id(&id_map, old).unwrap();
+    assert_eq!(named_recipes(&state), recipes_before);
+    let to_request: Vec<(Element, Element)> = state.to_request_recipes.iter().map(|recipe| *recipe).collect();
+    assert_eq!(to_request, vec![sort_recipe_tuple((new_id(mud), new_id(lava)))]);
+    assert_eq!(state.provenance.recipe_source((new_id(water), new_id(fire))), Some(0));
+    assert_eq!(state.dead_elements.evidence.len(), state.num_to_str.len());
+    assert_eq!(state.dead_elements.evidence[new_id(mud) as usize].confidence, DeadConfidence::Low);
+    let RequestPriority::ExplorerDepth(depths) = &state.request_priorities[0] else { panic!("not ExplorerDepth") };
+    assert_eq!(depths.len(), 1);
+    assert_eq!(depths.get(&new_id(mud)), Some(&1));
+}
@@ -0,0 +1,93 @@
+//! `clean_element_names` has to move everything that used a merged element onto the clean one
+
+use rustc_hash::FxHashMap;
+use infinite_craft_shenanigance::{Element, RecipesState, sort_recipe_tuple};
+use infinite_craft_shenanigance::dead_elements::{DeadConfidence, DeadEvidence};
+use infinite_craft_shenanigance::provenance::RecipeSource;
+use infinite_craft_shenanigance::recipe_merge::MergePolicy;
+use infinite_craft_shenanigance::recipe_requestor::RequestPriority;
+
+
+
+fn source(name: &str, timestamp: u128) -> RecipeSource {
+    RecipeSource { name: name.to_string(), proxy: None, timestamp }
+}
+
+
+fn result(state: &RecipesState, first: Element, second: Element) -> Option<&str> {
+    state.recipes_ing.get(&sort_recipe_tuple((first, second)))
+        .map(|&r| state.num_to_str[r as usize].as_str())
+}
+
+
+
+#[test]
+fn merge_and_rename_with_a_conflicting_recipe() {
+    let mut state = RecipesState::without_autosave();
+    state.set_merge_policy(MergePolicy::PreferExisting);
+    state.record_merge_conflicts();
+    state.provenance.sources = vec![source("old.json", 1), source("new.json", 2)];
+
+    let mut str_to_num = state.get_str_to_num_map();
+    let [water, fire, earth, wind] = ["Water", "Fire", "Earth", "Wind"].map(|name| str_to_num[name]);
+    let bad_water = state.variables_add_element_str("Water ", &mut str_to_num);
+
+    let recipes = [
+        (water, fire, "Steam", 0),
+        // becomes `Water + Fire` too, but that already is `Steam`
+        (fire, bad_water, "Smoke", 1),
+        (earth, bad_water, "Mud", 1),
+        (fire, earth, "Cafe\u{301}", 0),
+        (water, water, "Lake", 0),
+    ];
+    for (f, s, r, source) in recipes {
+        let r = state.variables_add_element_str(r, &mut str_to_num);
+        state.recipes_ing.insert((f, s), r);
+        state.provenance.recipe_sources.as_mut().unwrap().insert((f, s), source);
+    }
+    let cafe = str_to_num["Cafe\u{301}"];
+    state.to_request_recipes.insert((wind, bad_water));
+    // becomes `Water + Water`, which is known already
+    state.to_request_recipes.insert((bad_water, bad_water));
+
+    state.dead_elements.evidence = vec![DeadEvidence::default(); state.num_to_str.len()];
+    state.dead_elements.evidence[water as usize] = DeadEvidence { confidence: DeadConfidence::None, tested: 1, nothing: 0 };
+    state.dead_elements.evidence[bad_water as usize] = DeadEvidence { confidence: DeadConfidence::High, tested: 2, nothing: 2 };
+    let depths: FxHashMap<Element, usize> = [(water, 3), (bad_water, 1), (fire, 0)].into_iter().collect();
+    state.request_priorities.push(RequestPriority::ExplorerDepth(depths));
+
+    let report = state.clean_element_names();
+
+    assert_eq!(report.renamed, vec![("Cafe\u{301}".to_string(), "Café".to_string())]);
+    assert_eq!(report.merges.len(), 1);
+    assert_eq!((report.merges[0].from.as_str(), report.merges[0].into.as_str(), report.merges[0].recipes), ("Water ", "Water", 2));
+    assert_eq!(report.recipe_conflicts, 1);
+    assert_eq!(state.merging.conflicts.len(), 1);
+    assert_eq!(state.merging.conflicts[0].incoming, "Smoke");
+
+    // recipes
+    assert_eq!(result(&state, water, fire), Some("Steam"));
+    assert_eq!(result(&state, water, earth), Some("Mud"));
+    assert_eq!(result(&state, fire, earth), Some("Café"));
+    assert_eq!(state.num_to_str[cafe as usize], "Café");
+    assert_eq!(state.recipes_ing.len(), 4);
+    assert!(state.recipes_ing.iter().all(|(&(f, s), &r)| ![f, s, r].contains(&bad_water)));
+
+    // provenance
+    let source_name = |f, s| state.provenance.source_name(state.provenance.recipe_source((f, s))).to_string();
+    assert_eq!(source_name(water, fire), "old.json");
+    assert_eq!(source_name(water, earth), "new.json");
+    assert_eq!(state.provenance.recipe_sources.as_ref().unwrap().len(), 4);
+
+    // requests
+    let to_request: Vec<(Element, Element)> = state.to_request_recipes.iter().map(|recipe| *recipe).collect();
+    assert_eq!(to_request, vec![sort_recipe_tuple((water, wind))]);
+
+    // dead evidence and explorer depths
+    assert_eq!(state.dead_elements.evidence[water as usize], DeadEvidence { confidence: DeadConfidence::None, tested: 3, nothing: 2 });
+    assert_eq!(state.dead_elements.evidence[bad_water as usize], DeadEvidence::default());
+    let RequestPriority::ExplorerDepth(depths) = &state.request_priorities[0] else { panic!("not ExplorerDepth") };
+    assert_eq!(depths.get(&water), Some(&1));
+    assert_eq!(depths.get(&fire), Some(&0));
+    assert!(!depths.contains_key(&bad_water));
+}
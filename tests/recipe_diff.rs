@@ -0,0 +1,110 @@
+//! a diff saved as a patch has to turn the old database into the new one, and leave other databases alone
+
+use std::collections::BTreeSet;
+use infinite_craft_shenanigance::{RECIPE_FILES_FOLDER, RecipesState, sort_recipe_tuple};
+use infinite_craft_shenanigance::recipe_diff::RecipesDiff;
+
+
+
+fn state_with(recipes: &[(&str, &str, &str)]) -> RecipesState {
+    let mut state = RecipesState::without_autosave();
+    let mut str_to_num = state.get_str_to_num_map();
+    for (f, s, r) in recipes {
+        state.variables_add_recipe(f, s, r, &mut str_to_num);
+    }
+    state
+}
+
+
+fn old_state() -> RecipesState {
+    state_with(&[
+        ("Water", "Fire", "Steam"),
+        ("Water", "Earth", "Mud"),
+        ("Fire", "Earth", "Lava"),
+        ("Steam", "Steam", "Cloud"),
+        ("Steam", "Mud", "Swamp"),
+    ])
+}
+
+
+fn new_state() -> RecipesState {
+    state_with(&[
+        ("Water", "Fire", "Steam"),
+        ("Water", "Earth", "Mud"),
+        ("Fire", "Earth", "Magma"),
+        ("Steam", "Steam", "cloud"),
+        ("Mud", "Fire", "Brick"),
+    ])
+}
+
+
+fn named_recipes(state: &RecipesState) -> BTreeSet<[String; 3]> {
+    state.recipes_ing.iter()
+        .map(|(&(f, s), &r)| {
+            let [f, s, r] = [f, s, r].map(|e| state.num_to_str[e as usize].clone());
+            if f <= s { [f, s, r] } else { [s, f, r] }
+        })
+        .collect()
+}
+
+
+/// goes through a patch file like a teammate's patch would (every test needs its own file, they run in parallel)
+fn patch_round_trip(diff: &RecipesDiff, file_name: &str) -> RecipesDiff {
+    diff.save_patch(file_name).unwrap();
+    let loaded = RecipesDiff::load_patch(file_name);
+    std::fs::remove_file(format!("{RECIPE_FILES_FOLDER}/{file_name}")).unwrap();
+    loaded.unwrap()
+}
+
+
+
+#[test]
+fn diff_save_load_apply() {
+    let old = old_state();
+    let new = new_state();
+    let diff = old.diff(&new);
+    assert_eq!((diff.added.len(), diff.removed.len(), diff.case_only_changes.len(), diff.real_changes.len()), (1, 1, 1, 1));
+    let patch = patch_round_trip(&diff, "test patch (diff_save_load_apply).json");
+
+    let mut state = old_state();
+    state.track_provenance();
+    let str_to_num = state.get_str_to_num_map();
+    let (fire, earth, mud) = (str_to_num["Fire"], str_to_num["Earth"], str_to_num["Mud"]);
+    state.provenance.recipe_sources.as_mut().unwrap().insert((fire, earth), 0);
+    state.to_request_recipes.insert(sort_recipe_tuple((mud, fire)));
+
+    let report = state.apply_patch(&patch);
+    assert_eq!((report.applied, report.already_applied, report.conflicts.len()), (4, 0, 0));
+    assert_eq!(named_recipes(&state), named_recipes(&new));
+    assert_eq!(state.provenance.recipe_source((fire, earth)), None);
+    assert!(state.to_request_recipes.is_empty());
+
+    // a second time changes nothing
+    let element_count = state.num_to_str.len();
+    let report = state.apply_patch(&patch);
+    assert_eq!((report.applied, report.already_applied, report.conflicts.len()), (0, 4, 0));
+    assert_eq!(state.num_to_str.len(), element_count);
+    assert_eq!(named_recipes(&state), named_recipes(&new));
+}
+
+
+#[test]
+fn apply_to_another_database() {
+    let patch = patch_round_trip(&old_state().diff(&new_state()), "test patch (apply_to_another_database).json");
+
+    let mut state = state_with(&[("Fire", "Earth", "Obsidian")]);
+    let element_count = state.num_to_str.len();
+    let report = state.apply_patch(&patch);
+
+    // `Steam + Mud` isn't there (neither is `Steam`), so it's removed already.
+    // `Steam + Steam` and `Fire + Earth` don't have the expected old result
+    assert_eq!((report.applied, report.already_applied, report.conflicts.len()), (1, 1, 2));
+    let conflicts: BTreeSet<(&str, &str)> = report.conflicts.iter().map(|c| (c.old.as_str(), c.new.as_str())).collect();
+    assert_eq!(conflicts, BTreeSet::from([("", "cloud"), ("Obsidian", "Magma")]));
+
+    // only `Mud + Fire = Brick` created elements
+    let str_to_num = state.get_str_to_num_map();
+    assert_eq!(state.num_to_str.len(), element_count + 2);
+    assert!(str_to_num.contains_key("Brick") && str_to_num.contains_key("Mud"));
+    assert!(!str_to_num.contains_key("Steam"));
+}
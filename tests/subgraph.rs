@@ -0,0 +1,72 @@
+//! an extracted subgraph has to explore like the part of the database it came from
+
+use std::collections::{BTreeMap, BTreeSet};
+use infinite_craft_shenanigance::{Element, LayerExplorer, RecipesState, sort_recipe_tuple};
+
+
+
+/// Water, Fire, Earth, Wind
+const BASE_ELEMENTS: [Element; 4] = [0, 1, 2, 3];
+
+
+fn state_with(recipes: &[(&str, &str, &str)]) -> RecipesState {
+    let mut state = RecipesState::without_autosave();
+    let mut str_to_num = state.get_str_to_num_map();
+    // the explorer only looks up sorted recipes
+    for (f, s, r) in recipes {
+        let [f, s, r] = [f, s, r].map(|name| state.variables_add_element_str(name, &mut str_to_num));
+        state.recipes_ing.insert(sort_recipe_tuple((f, s)), r);
+    }
+    state
+}
+
+
+fn database() -> RecipesState {
+    state_with(&[
+        ("Water", "Fire", "Steam"),
+        ("Steam", "Earth", "Geyser"),
+        ("Water", "Earth", "Mud"),
+        ("Mud", "Fire", "Brick"),
+        ("Brick", "Steam", "Kiln"),
+        ("Wind", "Wind", "Nothing"),
+    ])
+}
+
+
+/// element name -> depth, exploring from the base elements
+fn explore(state: &RecipesState, max_steps: usize) -> BTreeMap<String, usize> {
+    LayerExplorer::start(state, &BASE_ELEMENTS, max_steps, false, false)
+        .depth_map()
+        .into_iter()
+        .map(|(e, depth)| (state.num_to_str[e as usize].clone(), depth))
+        .collect()
+}
+
+
+
+#[test]
+fn ancestors_explore_like_the_database() {
+    let state = database();
+    let geyser = state.get_str_to_num_map()["Geyser"];
+    let extracted = state.extract_ancestors(&[geyser]);
+
+    let ancestors: BTreeSet<&str> = ["Water", "Fire", "Earth", "Steam", "Geyser"].into();
+    let expected: BTreeMap<String, usize> = explore(&state, 3).into_iter()
+        .filter(|(name, _)| ancestors.contains(name.as_str()))
+        .collect();
+    assert_eq!(explore(&extracted, 3), expected);
+    assert_eq!(expected.get("Geyser"), Some(&2));
+    assert!(!extracted.get_str_to_num_map().contains_key("Brick"));
+}
+
+
+#[test]
+fn within_steps_explores_like_the_database() {
+    let state = database();
+    let extracted = state.extract_within_steps(&BASE_ELEMENTS, 2);
+
+    // extracting doesn't queue the missing recipes it ran into
+    assert!(state.to_request_recipes.is_empty());
+    assert_eq!(explore(&extracted, 2), explore(&state, 2));
+    assert!(!extracted.get_str_to_num_map().contains_key("Kiln"));
+}
@@ -0,0 +1,81 @@
+//! the text lineages format has to load back into exactly the recipes that got written
+
+use std::collections::BTreeSet;
+use infinite_craft_shenanigance::{CaseMode, RecipesState};
+use infinite_craft_shenanigance::lineages_file::{LineagesFileEntry, LineagesFileHeader, TextLineagesOptions, TextLineagesWriter};
+
+
+
+fn recipe(f: &str, s: &str, r: &str) -> [String; 3] {
+    [f.to_string(), s.to_string(), r.to_string()]
+}
+
+
+fn entries() -> Vec<LineagesFileEntry> {
+    vec![
+        LineagesFileEntry { element: "Steam".to_string(), lineages: vec![
+            vec![recipe("Water", "Fire", "Steam")],
+        ] },
+        LineagesFileEntry { element: "1 + 1 = 2".to_string(), lineages: vec![
+            vec![recipe("Water", "Fire", "Steam"), recipe("Steam", "Earth", "1 + 1 = 2")],
+            vec![recipe("Wind", "Fire", "Smoke"), recipe("Smoke", "Water", "1 + 1 = 2")],
+        ] },
+        LineagesFileEntry { element: "Math + Fire = Hot".to_string(), lineages: vec![
+            vec![recipe("Water", "Fire", "Steam"), recipe("Steam", "Earth", "1 + 1 = 2"), recipe("1 + 1 = 2", "Fire", "Math + Fire = Hot")],
+        ] },
+    ]
+}
+
+
+fn write_and_load(options: TextLineagesOptions) -> (String, BTreeSet<[String; 3]>) {
+    let header = LineagesFileHeader {
+        elements_ran: vec!["1 + 1 = 2".to_string(), "Water + Fire = Steam".to_string()],
+        case_mode: CaseMode::Neal,
+        depth_counts: vec![1, 1, 1],
+        total: 3,
+    };
+    let mut writer = TextLineagesWriter::new(Vec::new(), &header, options).unwrap();
+    for entry in entries() {
+        writer.write_entry(&entry).unwrap();
+    }
+    let text = String::from_utf8(writer.finish().unwrap()).unwrap();
+
+    let mut state = RecipesState::without_autosave();
+    state.load_recipes_from_text_lineages(text.as_bytes()).unwrap();
+    let loaded = state.recipes_ing.iter()
+        .map(|(&(f, s), &r)| {
+            let [f, s, r] = [f, s, r].map(|e| state.num_to_str[e as usize].clone());
+            // the file doesn't know the order of the ingredients
+            if f <= s { [f, s, r] } else { [s, f, r] }
+        })
+        .collect();
+    (text, loaded)
+}
+
+
+fn expected() -> BTreeSet<[String; 3]> {
+    entries().into_iter()
+        .flat_map(|entry| entry.lineages.into_iter().flatten())
+        .map(|[f, s, r]| if f <= s { [f, s, r] } else { [s, f, r] })
+        .collect()
+}
+
+
+
+#[test]
+fn round_trip() {
+    let (text, loaded) = write_and_load(TextLineagesOptions::default());
+    assert_eq!(loaded, expected(), "{text}");
+}
+
+
+#[test]
+fn round_trip_with_emojis_and_index() {
+    let emojis = [("Steam", "💨"), ("1 + 1 = 2", "🧮"), ("Water", "💧"), ("Fire", "🔥")]
+        .into_iter()
+        .map(|(name, emoji)| (name.to_string(), emoji.to_string()))
+        .collect();
+    let (text, loaded) = write_and_load(TextLineagesOptions { emojis: Some(emojis), index: true });
+    assert!(text.contains("// ===== Index ====="));
+    assert_eq!(loaded, expected(), "{text}");
+}